@@ -1,29 +1,313 @@
+use std::path::Path;
+
+use anyhow::Result;
+
 use crate::git;
-use crate::types::{PullResult, RepoStatus};
-
-pub async fn auto_pull_eligible(statuses: &mut [RepoStatus]) {
-    for status in statuses.iter_mut() {
-        for wt in &mut status.worktrees {
-            // Only eligible if: has branch, not detached, has upstream,
-            // behind > 0, ahead == 0
-            let eligible = wt.branch.is_some()
-                && wt.detached_head.is_none()
-                && matches!(wt.ahead_behind, Some((0, behind)) if behind > 0);
-
-            if !eligible {
-                continue;
+use crate::hooks::{Hooks, PulledWorktree};
+use crate::journal::{Journal, RefChange};
+use crate::policy;
+use crate::types::{
+    AppConfig, DirtyPolicy, GitOperation, LocalBranch, PullResult, PullStrategy, RepoKind,
+    RepoStatus,
+};
+
+/// Bring worktrees that are behind their upstream up to date.
+///
+/// Worktrees without local commits are fast-forwarded; `auto_pull_dirty`
+/// decides what happens to those with uncommitted changes. With the
+/// `rebase` strategy, clean worktrees that have diverged are rebased onto
+/// their upstream, and the rebase is aborted if it conflicts. Every branch
+/// that moves is recorded in `journal`, and `hooks` run in each worktree
+/// that was pulled.
+pub async fn auto_pull_eligible(
+    status: &mut RepoStatus,
+    config: &AppConfig,
+    journal: &Journal,
+    hooks: &Hooks,
+) {
+    let repo = &status.repo;
+    let mut pulled = Vec::new();
+
+    for (index, wt) in status.worktrees.iter_mut().enumerate() {
+        // Only eligible if: has branch, not detached, no rebase/merge/etc.
+        // in progress, has upstream, behind > 0
+        let eligible = wt.branch.is_some() && wt.detached_head.is_none() && wt.operation.is_none();
+        let Some((ahead, behind)) = wt.ahead_behind.filter(|_| eligible) else {
+            continue;
+        };
+        if behind == 0 {
+            continue;
+        }
+        // Local commits can only be kept by rebasing them
+        let rebase = ahead > 0;
+        if rebase && config.auto_pull_strategy != PullStrategy::Rebase {
+            continue;
+        }
+
+        // Move to the upstream commit status was computed against; it is
+        // already fetched, so this doesn't go back to the network
+        let Some(target) = wt.upstream.as_ref().and_then(|u| u.oid.clone()) else {
+            continue;
+        };
+
+        let branch = wt.branch.as_deref().unwrap_or_default();
+        if !policy::pull_allowed(config, &repo.path, branch) {
+            wt.pull_result = Some(PullResult::NotAllowed);
+            continue;
+        }
+
+        // Unknown status counts as dirty; rebasing always needs a clean tree.
+        // Submodules at another commit don't count when the pull updates them.
+        let is_dirty = wt.working_tree.is_none_or(|tree| {
+            if config.auto_pull_submodules {
+                tree.is_dirty_besides_submodule_pointers()
+            } else {
+                tree.is_dirty()
+            }
+        });
+        if is_dirty && (rebase || config.auto_pull_dirty == DirtyPolicy::Skip) {
+            wt.pull_result = Some(PullResult::Skipped("dirty".to_string()));
+            continue;
+        }
+        let autostash = is_dirty && config.auto_pull_dirty == DirtyPolicy::Autostash;
+
+        let old = git::rev_parse(&wt.path, RepoKind::NonBare, "HEAD").await;
+        let result = if rebase {
+            rebase_worktree(&wt.path, &target).await
+        } else {
+            git::merge_ff_only(&wt.path, &target, autostash)
+                .await
+                .map_or_else(
+                    |e| PullResult::Failed(e.to_string()),
+                    |()| PullResult::Pulled,
+                )
+        };
+
+        // Journal the move even if it only partly succeeded (e.g. the
+        // autostash didn't re-apply cleanly)
+        let new = git::rev_parse(&wt.path, RepoKind::NonBare, "HEAD").await;
+        let moved = match (old, new) {
+            (Ok(old), Ok(new)) if old != new => Some((old, new)),
+            _ => None,
+        };
+        if let Some((old, new)) = &moved {
+            journal.record(RefChange {
+                repo: repo.path.clone(),
+                kind: repo.kind,
+                worktree: Some(wt.path.clone()),
+                refname: format!("refs/heads/{}", branch),
+                old: old.clone(),
+                new: new.clone(),
+            });
+        }
+
+        // Refresh ahead/behind and changes after pull
+        wt.ahead_behind = git::ahead_behind(&wt.path).await;
+        wt.working_tree = git::working_tree_status(&wt.path).await;
+
+        // git succeeds even when re-applying the autostash conflicts
+        let stash_conflicted = autostash
+            && matches!(result, PullResult::Pulled)
+            && wt.working_tree.is_some_and(|tree| tree.conflicted > 0);
+        let mut result = if stash_conflicted {
+            PullResult::Failed("autostash conflicted, changes kept in stash".to_string())
+        } else {
+            result
+        };
+
+        // Bring submodules to the commits the new superproject commit records
+        if config.auto_pull_submodules
+            && moved.is_some()
+            && matches!(result, PullResult::Pulled | PullResult::Rebased)
+        {
+            if let Err(e) = git::update_submodules(&wt.path).await {
+                result = PullResult::Failed(format!("pulled, but submodule update failed: {}", e));
             }
+            wt.working_tree = git::working_tree_status(&wt.path).await;
+        }
+        wt.stale_submodules = git::stale_submodules(&wt.path).await;
+
+        if let (PullResult::Pulled | PullResult::Rebased, Some((old, new))) = (&result, moved) {
+            pulled.push((
+                index,
+                PulledWorktree {
+                    repo: repo.path.clone(),
+                    worktree: wt.path.clone(),
+                    branch: branch.to_string(),
+                    old,
+                    new,
+                },
+            ));
+        }
+        wt.pull_result = Some(result);
+    }
+
+    // Hooks of different worktrees run concurrently, within hook_concurrency
+    let runs: Vec<_> = pulled
+        .into_iter()
+        .map(|(index, pulled)| {
+            let hooks = hooks.clone();
+            let handle = tokio::spawn(async move { hooks.run_post_pull(&pulled).await });
+            (index, handle)
+        })
+        .collect();
+    for (index, handle) in runs {
+        match handle.await {
+            Ok(outcomes) => status.worktrees[index].hooks = outcomes,
+            Err(e) => eprintln!("Warning: hook task panicked: {}", e),
+        }
+    }
+}
 
-            match git::pull_ff_only(&wt.path).await {
-                Ok(()) => {
-                    wt.pull_result = Some(PullResult::Pulled);
-                    // Refresh ahead/behind after pull
-                    wt.ahead_behind = git::ahead_behind(&wt.path).await;
-                }
-                Err(e) => {
-                    wt.pull_result = Some(PullResult::Failed(e.to_string()));
-                }
+/// Rebase the worktree's branch onto `target`, aborting on conflict so the
+/// branch and working tree are left as they were.
+async fn rebase_worktree(worktree_path: &Path, target: &str) -> PullResult {
+    let Err(e) = git::rebase(worktree_path, target).await else {
+        return PullResult::Rebased;
+    };
+    // A rebase that never started (e.g. untracked files in the way) has
+    // nothing to abort
+    if git::operation_in_progress(worktree_path).await != Some(GitOperation::Rebase) {
+        return PullResult::Failed(e.to_string());
+    }
+    match git::rebase_abort(worktree_path).await {
+        Ok(()) => PullResult::RebaseAborted,
+        Err(abort_err) => PullResult::Failed(format!(
+            "rebase conflicted and could not be aborted: {}",
+            abort_err
+        )),
+    }
+}
+
+/// Fast-forward local branches that are not checked out anywhere and are
+/// strictly behind their upstream, by moving the ref directly.
+///
+/// Each branch is moved to the upstream commit recorded when branches were
+/// listed, and the ref update is guarded by the branch commit recorded
+/// then, so a branch that moved since it was inspected is left alone and
+/// reported as failed.
+pub async fn fast_forward_branches(status: &mut RepoStatus, config: &AppConfig, journal: &Journal) {
+    let repo_path = status.repo.path.clone();
+    let kind = status.repo.kind;
+
+    for branch in &mut status.branches {
+        let eligible = branch.worktree.is_none()
+            && !branch.upstream_gone
+            && matches!(branch.ahead_behind, Some((0, behind)) if behind > 0);
+
+        if !eligible {
+            continue;
+        }
+        if !policy::pull_allowed(config, &repo_path, &branch.name) {
+            branch.pull_result = Some(PullResult::NotAllowed);
+            continue;
+        }
+
+        match fast_forward_branch(&repo_path, kind, branch, journal).await {
+            Ok(()) => {
+                branch.pull_result = Some(PullResult::Pulled);
+                branch.ahead_behind = Some((0, 0));
+            }
+            Err(e) => {
+                branch.pull_result = Some(PullResult::Failed(e.to_string()));
             }
         }
     }
 }
+
+async fn fast_forward_branch(
+    repo_path: &Path,
+    kind: RepoKind,
+    branch: &LocalBranch,
+    journal: &Journal,
+) -> Result<()> {
+    let Some(new) = branch.upstream_oid.clone() else {
+        anyhow::bail!("no upstream");
+    };
+    let refname = format!("refs/heads/{}", branch.name);
+    let old = branch.oid.clone();
+
+    if !git::is_ancestor(repo_path, kind, &old, &new).await? {
+        anyhow::bail!("not a fast-forward");
+    }
+    git::update_ref(repo_path, kind, &refname, &new, &old, "fast-forward").await?;
+    journal.record(RefChange {
+        repo: repo_path.to_path_buf(),
+        kind,
+        worktree: None,
+        refname,
+        old,
+        new,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Arc;
+
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::status;
+    use crate::types::{DiscoveredRepo, FetchOutcome, FetchResult, SkipReason};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {:?}", args, output);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// A bare clone of `seed` whose `main` tracks `origin/main`
+    fn bare_clone() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let seed = dir.path().join("seed");
+        let bare = dir.path().join("proj.git");
+        git(dir.path(), &["init", "-q", "-b", "main", "seed"]);
+        git(&seed, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(dir.path(), &["clone", "-q", "--bare", "seed", "proj.git"]);
+        let refspec = "+refs/heads/*:refs/remotes/origin/*";
+        git(&bare, &["config", "remote.origin.fetch", refspec]);
+        git(&bare, &["config", "branch.main.remote", "origin"]);
+        git(&bare, &["config", "branch.main.merge", "refs/heads/main"]);
+        git(&bare, &["fetch", "-q", "origin"]);
+        (dir, seed, bare)
+    }
+
+    #[tokio::test]
+    async fn fast_forwards_a_bare_repos_head_branch() {
+        let (dir, seed, bare) = bare_clone();
+        git(&seed, &["commit", "-q", "--allow-empty", "-m", "second"]);
+        git(&bare, &["fetch", "-q", "origin"]);
+
+        let result = FetchResult {
+            repo: DiscoveredRepo {
+                path: bare.clone(),
+                kind: RepoKind::Bare,
+            },
+            outcome: FetchOutcome::Skipped(SkipReason::Disabled),
+        };
+        let mut status = status::build_repo_status(result, Arc::new(Semaphore::new(1)), true).await;
+        let config = AppConfig {
+            ff_branches: true,
+            ..AppConfig::default()
+        };
+        let journal = Journal::at(dir.path().join("journal.tsv"));
+        fast_forward_branches(&mut status, &config, &journal).await;
+
+        let main = status.branches.iter().find(|b| b.name == "main").unwrap();
+        assert!(matches!(main.pull_result, Some(PullResult::Pulled)));
+        assert_eq!(
+            git(&bare, &["rev-parse", "refs/heads/main"]),
+            git(&seed, &["rev-parse", "HEAD"])
+        );
+    }
+}
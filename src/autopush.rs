@@ -0,0 +1,265 @@
+use crate::git;
+use crate::policy::glob_match;
+use crate::status;
+use crate::types::{AppConfig, PushResult, RepoStatus};
+
+/// Push worktrees that are clean and strictly ahead of their push
+/// destination (`@{push}`, which may be the upstream) to their push remote,
+/// limited to branches in `auto_push_branches`. A branch that was never
+/// pushed there is pushed if it is ahead of its upstream.
+///
+/// Pushes are never forced, so a push the remote would have to reject
+/// (e.g. a diverged fork branch) is reported as failed.
+pub async fn auto_push_eligible(status: &mut RepoStatus, config: &AppConfig) {
+    for wt in &mut status.worktrees {
+        let (Some(branch), Some(target)) = (&wt.branch, &wt.push) else {
+            continue;
+        };
+        let ahead_of_upstream = matches!(wt.ahead_behind, Some((ahead, 0)) if ahead > 0);
+        let ahead_of_destination = if target.is_upstream {
+            ahead_of_upstream
+        } else {
+            match &wt.vs_push {
+                Some(cmp) => matches!(cmp.ahead_behind, (ahead, 0) if ahead > 0),
+                // No tracking ref: never pushed there, so only push new
+                // commits the upstream doesn't have yet either
+                None => ahead_of_upstream,
+            }
+        };
+        // Only eligible if: on a branch with no operation in progress,
+        // nothing uncommitted, ahead > 0 and behind == 0
+        let eligible = wt.detached_head.is_none()
+            && wt.operation.is_none()
+            && wt.working_tree.is_some_and(|tree| !tree.is_dirty())
+            && ahead_of_destination;
+        if !eligible {
+            continue;
+        }
+
+        let allowed = config.auto_push_branches.is_empty()
+            || config
+                .auto_push_branches
+                .iter()
+                .any(|pattern| glob_match(pattern, branch));
+        if !allowed {
+            continue;
+        }
+
+        let result = git::push(
+            &wt.path,
+            &target.remote,
+            branch,
+            &target.remote_ref,
+            config.push_dry_run,
+        )
+        .await;
+        wt.push_result = Some(match result {
+            Ok(()) if config.push_dry_run => PushResult::WouldPush,
+            Ok(()) => PushResult::Pushed,
+            Err(e) => PushResult::PushFailed(e.to_string()),
+        });
+
+        // Pushing also moves the local tracking ref of the destination
+        if matches!(wt.push_result, Some(PushResult::Pushed)) {
+            wt.ahead_behind = git::ahead_behind(&wt.path).await;
+            if let Some(target) = wt.push.as_ref().filter(|target| !target.is_upstream) {
+                let tracking = target.tracking.clone();
+                wt.vs_push = status::compare_with(wt, &tracking).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Arc;
+
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::types::{DiscoveredRepo, FetchOutcome, FetchResult, RepoKind, SkipReason};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {:?}", args, output);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// A clone of a bare remote, on `main` tracking `origin/main`
+    fn clone_of_bare_remote() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        let work = dir.path().join("work");
+        git(dir.path(), &["init", "-q", "--bare", "remote.git"]);
+        git(dir.path(), &["clone", "-q", "remote.git", "work"]);
+        git(&work, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        commit(&work, "first");
+        git(&work, &["push", "-q", "-u", "origin", "main"]);
+        (dir, remote, work)
+    }
+
+    fn commit(work: &Path, name: &str) {
+        std::fs::write(work.join(name), name).unwrap();
+        git(work, &["add", name]);
+        git(work, &["commit", "-q", "-m", name]);
+    }
+
+    fn remote_main(remote: &Path) -> String {
+        git(remote, &["rev-parse", "refs/heads/main"])
+    }
+
+    async fn status_of(work: &Path) -> RepoStatus {
+        let result = FetchResult {
+            repo: DiscoveredRepo {
+                path: work.to_path_buf(),
+                kind: RepoKind::NonBare,
+            },
+            outcome: FetchOutcome::Skipped(SkipReason::Disabled),
+        };
+        status::build_repo_status(result, Arc::new(Semaphore::new(1)), false).await
+    }
+
+    fn push_config() -> AppConfig {
+        AppConfig {
+            auto_push: true,
+            ..AppConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn pushes_a_clean_branch_that_is_ahead() {
+        let (_dir, remote, work) = clone_of_bare_remote();
+        commit(&work, "second");
+
+        let mut status = status_of(&work).await;
+        auto_push_eligible(&mut status, &push_config()).await;
+
+        let wt = &status.worktrees[0];
+        assert!(matches!(wt.push_result, Some(PushResult::Pushed)));
+        assert_eq!(wt.ahead_behind, Some((0, 0)));
+        assert_eq!(remote_main(&remote), git(&work, &["rev-parse", "HEAD"]));
+    }
+
+    #[tokio::test]
+    async fn dry_run_leaves_the_remote_alone() {
+        let (_dir, remote, work) = clone_of_bare_remote();
+        let before = remote_main(&remote);
+        commit(&work, "second");
+
+        let mut status = status_of(&work).await;
+        let config = AppConfig {
+            push_dry_run: true,
+            ..push_config()
+        };
+        auto_push_eligible(&mut status, &config).await;
+
+        assert!(matches!(
+            status.worktrees[0].push_result,
+            Some(PushResult::WouldPush)
+        ));
+        assert_eq!(remote_main(&remote), before);
+    }
+
+    #[tokio::test]
+    async fn only_pushes_allowed_branches() {
+        let (_dir, remote, work) = clone_of_bare_remote();
+        let before = remote_main(&remote);
+        commit(&work, "second");
+
+        let mut status = status_of(&work).await;
+        let config = AppConfig {
+            auto_push_branches: vec!["feature/*".to_string()],
+            ..push_config()
+        };
+        auto_push_eligible(&mut status, &config).await;
+
+        assert!(status.worktrees[0].push_result.is_none());
+        assert_eq!(remote_main(&remote), before);
+    }
+
+    #[tokio::test]
+    async fn pushes_to_a_fork_until_it_has_caught_up() {
+        let (dir, remote, work) = clone_of_bare_remote();
+        let fork = dir.path().join("fork.git");
+        git(dir.path(), &["init", "-q", "--bare", "fork.git"]);
+        git(&work, &["remote", "add", "fork", &fork.to_string_lossy()]);
+        // git can't resolve @{push} for this with the default push.default
+        git(&work, &["config", "push.default", "simple"]);
+        git(&work, &["config", "branch.main.pushRemote", "fork"]);
+        let upstream = remote_main(&remote);
+        commit(&work, "second");
+
+        // Never pushed to the fork, so there is no fork/main yet
+        let mut status = status_of(&work).await;
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(matches!(
+            status.worktrees[0].push_result,
+            Some(PushResult::Pushed)
+        ));
+        assert_eq!(remote_main(&fork), git(&work, &["rev-parse", "HEAD"]));
+        assert_eq!(remote_main(&remote), upstream);
+
+        // Still ahead of the upstream, but the fork is up to date
+        git(&work, &["fetch", "-q", "fork"]);
+        let mut status = status_of(&work).await;
+        assert_eq!(status.worktrees[0].ahead_behind, Some((1, 0)));
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(status.worktrees[0].push_result.is_none());
+    }
+
+    #[tokio::test]
+    async fn does_not_push_to_an_empty_fork_without_new_commits() {
+        let (dir, remote, work) = clone_of_bare_remote();
+        let fork = dir.path().join("fork.git");
+        git(dir.path(), &["init", "-q", "--bare", "fork.git"]);
+        git(&work, &["remote", "add", "fork", &fork.to_string_lossy()]);
+        git(&work, &["config", "push.default", "simple"]);
+        git(&work, &["config", "branch.main.pushRemote", "fork"]);
+
+        // In sync with the upstream
+        let mut status = status_of(&work).await;
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(status.worktrees[0].push_result.is_none());
+
+        // Behind the upstream
+        let other = dir.path().join("other");
+        git(dir.path(), &["clone", "-q", "remote.git", "other"]);
+        commit(&other, "upstream");
+        git(&other, &["push", "-q", "origin", "main"]);
+        git(&work, &["fetch", "-q", "origin"]);
+        let mut status = status_of(&work).await;
+        assert_eq!(status.worktrees[0].ahead_behind, Some((0, 1)));
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(status.worktrees[0].push_result.is_none());
+
+        let fork_branches = git(&fork, &["for-each-ref", "refs/heads"]);
+        assert_eq!(fork_branches, "");
+        assert_eq!(remote_main(&remote), git(&other, &["rev-parse", "HEAD"]));
+    }
+
+    #[tokio::test]
+    async fn skips_dirty_or_up_to_date_worktrees() {
+        let (_dir, remote, work) = clone_of_bare_remote();
+        let before = remote_main(&remote);
+
+        let mut status = status_of(&work).await;
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(status.worktrees[0].push_result.is_none());
+
+        commit(&work, "second");
+        std::fs::write(work.join("second"), "edited").unwrap();
+        let mut status = status_of(&work).await;
+        auto_push_eligible(&mut status, &push_config()).await;
+        assert!(status.worktrees[0].push_result.is_none());
+
+        assert_eq!(remote_main(&remote), before);
+    }
+}
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// Scan directories for git repos, fetch remotes in parallel,
@@ -6,6 +6,9 @@ use std::path::PathBuf;
 #[derive(Parser, Debug)]
 #[command(name = "git-worktree-refresh", version, about)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Directories to scan (repeatable)
     #[arg(short = 'd', long = "directories", value_name = "DIR")]
     pub directories: Vec<PathBuf>,
@@ -14,6 +17,14 @@ pub struct Cli {
     #[arg(short = 'j', long, value_name = "N")]
     pub concurrency: Option<usize>,
 
+    /// Max parallel fetches per remote host
+    #[arg(long, value_name = "N")]
+    pub host_concurrency: Option<usize>,
+
+    /// Max worktrees inspected in parallel for status (default: CPU count)
+    #[arg(long, value_name = "N")]
+    pub status_concurrency: Option<usize>,
+
     /// Disable emoji in output
     #[arg(long)]
     pub no_emoji: bool,
@@ -22,10 +33,30 @@ pub struct Cli {
     #[arg(long)]
     pub no_fetch: bool,
 
+    /// Fetch even if the repo was fetched within min_fetch_interval
+    #[arg(long)]
+    pub force_fetch: bool,
+
     /// Pull changes into FF-safe worktrees
     #[arg(long)]
     pub auto_pull: bool,
 
+    /// Fast-forward local branches that aren't checked out and are behind upstream
+    #[arg(long)]
+    pub ff_branches: bool,
+
+    /// Push clean worktrees that are strictly ahead to their push remote
+    #[arg(long)]
+    pub auto_push: bool,
+
+    /// Only auto-push branches matching PATTERN (repeatable, e.g. 'feature/*')
+    #[arg(long = "push-branch", value_name = "PATTERN")]
+    pub push_branches: Vec<String>,
+
+    /// Check what auto-push would push without pushing
+    #[arg(long)]
+    pub push_dry_run: bool,
+
     /// Custom config file path
     #[arg(short = 'c', long = "config", value_name = "FILE")]
     pub config: Option<PathBuf>,
@@ -37,4 +68,58 @@ pub struct Cli {
     /// Show repos even if no changes fetched
     #[arg(long)]
     pub show_all: bool,
+
+    /// Report ahead/behind for every local branch, not just checked-out ones
+    #[arg(long)]
+    pub all_branches: bool,
+
+    /// Only show worktrees matching the filter (repeatable; all must match)
+    #[arg(long = "filter", value_name = "FILTER", value_enum)]
+    pub filters: Vec<FilterArg>,
+
+    /// Only show repos with stashes older than N days
+    #[arg(long, value_name = "DAYS")]
+    pub stashes_older_than: Option<u64>,
+
+    /// Output format (default: text)
+    #[arg(long, value_name = "FORMAT", value_enum)]
+    pub format: Option<FormatArg>,
+
+    /// Suppress progress output
+    #[arg(short = 'q', long)]
+    pub quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Restore the branches moved by the last run (auto-pull, --ff-branches)
+    Undo,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterArg {
+    /// Any uncommitted change, including untracked files
+    Dirty,
+    /// Changes staged in the index
+    Staged,
+    /// Modified tracked files not yet staged
+    Unstaged,
+    /// Untracked files
+    Untracked,
+    /// Unresolved merge conflicts
+    Conflicted,
+    /// A rebase, merge, cherry-pick, revert or bisect in progress
+    InProgress,
+    /// Upstream branch was deleted on the remote
+    Gone,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatArg {
+    /// Coloured, human-readable report
+    Text,
+    /// One JSON document with every repo and a summary, printed at the end
+    Json,
+    /// One JSON event per line (discovery, fetches, status, pulls, summary) as they happen
+    Ndjson,
 }
@@ -1,20 +1,104 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
 use serde::Deserialize;
 
-use crate::cli::Cli;
-use crate::types::AppConfig;
+use crate::cli::{Cli, FilterArg, FormatArg};
+use crate::types::{
+    AppConfig, DirtyPolicy, InterruptPolicy, OutputFormat, PolicyRule, PostPullHook, PullStrategy,
+    WorktreeFilter,
+};
 
 #[derive(Debug, Deserialize, Default)]
 pub struct FileConfig {
     pub directories: Option<Vec<String>>,
     pub concurrency: Option<usize>,
+    pub host_concurrency: Option<HostConcurrencyConfig>,
+    pub status_concurrency: Option<usize>,
     pub fetch: Option<bool>,
+    pub min_fetch_interval: Option<DurationValue>,
+    pub on_interrupt: Option<InterruptPolicy>,
     pub emoji: Option<bool>,
-    pub auto_pull: Option<bool>,
+    pub auto_pull: Option<AutoPullValue>,
+    pub groups: Option<HashMap<String, Vec<String>>>,
+    pub post_pull: Option<Vec<PostPullHook>>,
+    pub auto_push: Option<AutoPushValue>,
+    pub hook_concurrency: Option<usize>,
+    pub ff_branches: Option<bool>,
     pub max_depth: Option<usize>,
     pub show_all: Option<bool>,
+    pub all_branches: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct HostConcurrencyConfig {
+    pub default: Option<usize>,
+    pub hosts: Option<HashMap<String, usize>>,
+}
+
+/// `auto_pull: true`, or a map with settings (`enabled` defaults to true)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AutoPullValue {
+    Enabled(bool),
+    Settings(AutoPullConfig),
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AutoPullConfig {
+    pub enabled: Option<bool>,
+    pub dirty: Option<DirtyPolicy>,
+    pub strategy: Option<PullStrategy>,
+    pub submodules: Option<bool>,
+    pub allow: Option<PolicyRule>,
+    pub deny: Option<PolicyRule>,
+}
+
+/// `auto_push: true`, or a map with settings (`enabled` defaults to true)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AutoPushValue {
+    Enabled(bool),
+    Settings(AutoPushConfig),
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AutoPushConfig {
+    pub enabled: Option<bool>,
+    pub branches: Option<Vec<String>>,
+    pub dry_run: Option<bool>,
+}
+
+/// A duration given either as plain seconds (`600`) or with a unit (`10m`).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DurationValue {
+    Seconds(u64),
+    Text(String),
+}
+
+fn parse_duration(value: &DurationValue) -> Result<Duration> {
+    let text = match value {
+        DurationValue::Seconds(secs) => return Ok(Duration::from_secs(*secs)),
+        DurationValue::Text(text) => text.trim(),
+    };
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid duration: {:?}", text))?;
+    let multiplier = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => anyhow::bail!("invalid duration unit in {:?} (use s, m, h or d)", text),
+    };
+    Ok(Duration::from_secs(number * multiplier))
 }
 
 fn default_config_path() -> Option<PathBuf> {
@@ -22,6 +106,13 @@ fn default_config_path() -> Option<PathBuf> {
         .map(|dirs| dirs.config_dir().join("config.yaml"))
 }
 
+/// Where run state (the journal, fetch times) is kept
+pub fn state_dir() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "git-worktree-refresh")?;
+    let dir = dirs.state_dir().unwrap_or_else(|| dirs.data_local_dir());
+    Some(dir.to_path_buf())
+}
+
 fn expand_tilde(path: &str) -> PathBuf {
     if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = dirs_home() {
@@ -31,6 +122,17 @@ fn expand_tilde(path: &str) -> PathBuf {
     PathBuf::from(path)
 }
 
+fn expand_glob(glob: &str) -> String {
+    expand_tilde(glob).to_string_lossy().to_string()
+}
+
+fn expand_rule(rule: PolicyRule) -> PolicyRule {
+    PolicyRule {
+        repos: rule.repos.iter().map(|g| expand_glob(g)).collect(),
+        ..rule
+    }
+}
+
 fn dirs_home() -> Option<PathBuf> {
     directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf())
 }
@@ -60,14 +162,85 @@ pub fn resolve_config(cli: &Cli) -> Result<AppConfig> {
             if let Some(c) = file_cfg.concurrency {
                 config.concurrency = c;
             }
+            if let Some(hc) = file_cfg.host_concurrency {
+                config.host_limits.default = hc.default;
+                if let Some(hosts) = hc.hosts {
+                    config.host_limits.hosts = hosts
+                        .into_iter()
+                        .map(|(host, limit)| (host.to_lowercase(), limit))
+                        .collect();
+                }
+            }
+            if let Some(sc) = file_cfg.status_concurrency {
+                config.status_concurrency = sc;
+            }
             if let Some(f) = file_cfg.fetch {
                 config.fetch = f;
             }
+            if let Some(interval) = file_cfg.min_fetch_interval {
+                config.min_fetch_interval = Some(parse_duration(&interval)?);
+            }
+            if let Some(policy) = file_cfg.on_interrupt {
+                config.on_interrupt = policy;
+            }
             if let Some(e) = file_cfg.emoji {
                 config.emoji = e;
             }
-            if let Some(ap) = file_cfg.auto_pull {
-                config.auto_pull = ap;
+            match file_cfg.auto_pull {
+                Some(AutoPullValue::Enabled(ap)) => config.auto_pull = ap,
+                Some(AutoPullValue::Settings(ap)) => {
+                    config.auto_pull = ap.enabled.unwrap_or(true);
+                    if let Some(dirty) = ap.dirty {
+                        config.auto_pull_dirty = dirty;
+                    }
+                    if let Some(strategy) = ap.strategy {
+                        config.auto_pull_strategy = strategy;
+                    }
+                    if let Some(submodules) = ap.submodules {
+                        config.auto_pull_submodules = submodules;
+                    }
+                    if let Some(allow) = ap.allow {
+                        config.auto_pull_policy.allow = expand_rule(allow);
+                    }
+                    if let Some(deny) = ap.deny {
+                        config.auto_pull_policy.deny = expand_rule(deny);
+                    }
+                }
+                None => {}
+            }
+            if let Some(hooks) = file_cfg.post_pull {
+                config.post_pull = hooks
+                    .into_iter()
+                    .map(|hook| PostPullHook {
+                        repos: hook.repos.iter().map(|g| expand_glob(g)).collect(),
+                        ..hook
+                    })
+                    .collect();
+            }
+            match file_cfg.auto_push {
+                Some(AutoPushValue::Enabled(push)) => config.auto_push = push,
+                Some(AutoPushValue::Settings(push)) => {
+                    config.auto_push = push.enabled.unwrap_or(true);
+                    if let Some(branches) = push.branches {
+                        config.auto_push_branches = branches;
+                    }
+                    if let Some(dry_run) = push.dry_run {
+                        config.push_dry_run = dry_run;
+                    }
+                }
+                None => {}
+            }
+            if let Some(hc) = file_cfg.hook_concurrency {
+                config.hook_concurrency = hc;
+            }
+            if let Some(groups) = file_cfg.groups {
+                config.groups = groups
+                    .into_iter()
+                    .map(|(name, globs)| (name, globs.iter().map(|g| expand_glob(g)).collect()))
+                    .collect();
+            }
+            if let Some(ff) = file_cfg.ff_branches {
+                config.ff_branches = ff;
             }
             if let Some(md) = file_cfg.max_depth {
                 config.max_depth = md;
@@ -75,6 +248,9 @@ pub fn resolve_config(cli: &Cli) -> Result<AppConfig> {
             if let Some(sa) = file_cfg.show_all {
                 config.show_all = sa;
             }
+            if let Some(ab) = file_cfg.all_branches {
+                config.all_branches = ab;
+            }
         }
     }
 
@@ -85,21 +261,78 @@ pub fn resolve_config(cli: &Cli) -> Result<AppConfig> {
     if let Some(c) = cli.concurrency {
         config.concurrency = c;
     }
+    if let Some(hc) = cli.host_concurrency {
+        config.host_limits.default = Some(hc);
+    }
+    if let Some(sc) = cli.status_concurrency {
+        config.status_concurrency = sc;
+    }
     if cli.no_fetch {
         config.fetch = false;
     }
+    if cli.force_fetch {
+        config.min_fetch_interval = None;
+    }
     if cli.no_emoji {
         config.emoji = false;
     }
     if cli.auto_pull {
         config.auto_pull = true;
     }
+    if cli.ff_branches {
+        config.ff_branches = true;
+    }
+    if cli.auto_push {
+        config.auto_push = true;
+    }
+    if !cli.push_branches.is_empty() {
+        config.auto_push_branches = cli.push_branches.clone();
+    }
+    if cli.push_dry_run {
+        config.push_dry_run = true;
+    }
     if let Some(md) = cli.max_depth {
         config.max_depth = md;
     }
     if cli.show_all {
         config.show_all = true;
     }
+    if cli.all_branches {
+        config.all_branches = true;
+    }
+    config.filters = cli
+        .filters
+        .iter()
+        .map(|f| match f {
+            FilterArg::Dirty => WorktreeFilter::Dirty,
+            FilterArg::Staged => WorktreeFilter::Staged,
+            FilterArg::Unstaged => WorktreeFilter::Unstaged,
+            FilterArg::Untracked => WorktreeFilter::Untracked,
+            FilterArg::Conflicted => WorktreeFilter::Conflicted,
+            FilterArg::InProgress => WorktreeFilter::InProgress,
+            FilterArg::Gone => WorktreeFilter::Gone,
+        })
+        .collect();
+    if let Some(days) = cli.stashes_older_than {
+        config.stashes_older_than = Some(Duration::from_secs(days * 24 * 60 * 60));
+    }
+    if let Some(format) = cli.format {
+        config.format = match format {
+            FormatArg::Text => OutputFormat::Text,
+            FormatArg::Json => OutputFormat::Json,
+            FormatArg::Ndjson => OutputFormat::Ndjson,
+        };
+    }
+    if cli.quiet {
+        config.quiet = true;
+    }
+
+    let policy = &config.auto_pull_policy;
+    for name in policy.allow.groups.iter().chain(&policy.deny.groups) {
+        if !config.groups.contains_key(name) {
+            eprintln!("Warning: auto_pull rules refer to unknown group {:?}", name);
+        }
+    }
 
     if config.directories.is_empty() {
         anyhow::bail!(
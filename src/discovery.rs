@@ -52,13 +52,11 @@ fn scan_dir(
         }
     };
 
-    for entry in entries {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+    // Sort so repos are reported in the same order on every run and platform
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
 
-        let path = entry.path();
+    for path in paths {
         if !path.is_dir() {
             continue;
         }
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::config;
+use crate::policy::canonical_path;
+
+/// When each repo was last fetched successfully, for `min_fetch_interval`.
+///
+/// `FETCH_HEAD` can't be used for this since git rewrites it even when a
+/// fetch fails. Kept in the state directory as one tab-separated line per
+/// repo: `unix_time  path`.
+pub struct FetchTimes {
+    path: Option<PathBuf>,
+    times: Mutex<HashMap<PathBuf, u64>>,
+}
+
+impl FetchTimes {
+    pub fn load() -> Self {
+        let path = config::state_dir().map(|dir| dir.join("fetch-times.tsv"));
+        let times = path.as_deref().map(read).unwrap_or_default();
+        Self {
+            path,
+            times: Mutex::new(times),
+        }
+    }
+
+    /// Time since the repo was last fetched successfully
+    pub fn age(&self, repo: &Path) -> Option<Duration> {
+        let fetched = *self.times.lock().unwrap().get(&canonical_path(repo))?;
+        // A timestamp in the future (clock skew) counts as just fetched
+        Some(Duration::from_secs(now().saturating_sub(fetched)))
+    }
+
+    pub fn record_success(&self, repo: &Path) {
+        self.times
+            .lock()
+            .unwrap()
+            .insert(canonical_path(repo), now());
+    }
+
+    /// Write the times back, keeping newer ones another run saved meanwhile
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = self.write(path) {
+            eprintln!(
+                "Warning: failed to save fetch times {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let mut times = read(path);
+        for (repo, &time) in self.times.lock().unwrap().iter() {
+            let saved = times.entry(repo.clone()).or_default();
+            *saved = (*saved).max(time);
+        }
+
+        let mut contents = String::new();
+        for (repo, time) in &times {
+            contents.push_str(&format!("{}\t{}\n", time, repo.display()));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Replace atomically so a concurrent run never reads half a file
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> HashMap<PathBuf, u64> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (time, repo) = line.split_once('\t')?;
+            Some((PathBuf::from(repo), time.parse().ok()?))
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
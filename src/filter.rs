@@ -0,0 +1,42 @@
+use std::time::{Duration, SystemTime};
+
+use crate::types::{RepoStatus, WorktreeFilter, WorktreeInfo};
+
+/// Whether `wt` passes every filter (trivially true when there are none)
+pub fn worktree_matches(filters: &[WorktreeFilter], wt: &WorktreeInfo) -> bool {
+    filters.iter().all(|filter| matches_filter(*filter, wt))
+}
+
+/// Worktrees of `status` that pass all filters
+pub fn matching_worktrees<'a>(
+    filters: &[WorktreeFilter],
+    status: &'a RepoStatus,
+) -> Vec<&'a WorktreeInfo> {
+    status
+        .worktrees
+        .iter()
+        .filter(|wt| worktree_matches(filters, wt))
+        .collect()
+}
+
+/// Whether the repo has a stash older than `min_age`
+pub fn has_stash_older_than(status: &RepoStatus, min_age: Duration) -> bool {
+    let now = SystemTime::now();
+    status
+        .stashes
+        .iter()
+        .any(|stash| now.duration_since(stash.created).unwrap_or_default() > min_age)
+}
+
+fn matches_filter(filter: WorktreeFilter, wt: &WorktreeInfo) -> bool {
+    let tree = wt.working_tree.unwrap_or_default();
+    match filter {
+        WorktreeFilter::Dirty => tree.is_dirty(),
+        WorktreeFilter::Staged => tree.staged > 0,
+        WorktreeFilter::Unstaged => tree.unstaged > 0,
+        WorktreeFilter::Untracked => tree.untracked > 0,
+        WorktreeFilter::Conflicted => tree.conflicted > 0,
+        WorktreeFilter::InProgress => wt.operation.is_some(),
+        WorktreeFilter::Gone => wt.upstream_gone(),
+    }
+}
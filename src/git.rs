@@ -1,9 +1,17 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::time::{Duration, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
 use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
 
-use crate::types::{FetchOutcome, RepoKind, WorktreeInfo};
+use crate::interrupt::FetchGroup;
+use crate::policy::canonical_path;
+use crate::types::{
+    FetchOutcome, GitOperation, LocalBranch, RepoKind, StashEntry, WorkingTreeStatus, WorktreeInfo,
+};
 
 pub async fn check_git_available() -> Result<()> {
     let output = Command::new("git")
@@ -17,16 +25,75 @@ pub async fn check_git_available() -> Result<()> {
     Ok(())
 }
 
-pub async fn has_remote(repo_path: &Path, kind: RepoKind) -> bool {
-    let result = git_cmd(repo_path, kind, &["remote"]).await;
-    match result {
-        Ok(output) => !output.trim().is_empty(),
-        Err(_) => false,
-    }
+/// Fetch URLs of all configured remotes (empty if the repo has none)
+pub async fn remote_urls(repo_path: &Path, kind: RepoKind) -> Vec<String> {
+    let output = match git_cmd(repo_path, kind, &["remote", "-v"]).await {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+    // Lines look like: origin\tgit@github.com:org/repo.git (fetch)
+    let mut urls: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.strip_suffix(" (fetch)"))
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(_, url)| url.to_string())
+        .collect();
+    urls.dedup();
+    urls
 }
 
-pub async fn fetch_all(repo_path: &Path, kind: RepoKind) -> FetchOutcome {
-    let result = git_cmd_full(repo_path, kind, &["fetch", "--all", "--prune"]).await;
+/// How long a cancelled fetch gets to exit before it is killed
+const FETCH_STOP_GRACE: Duration = Duration::from_secs(3);
+
+/// Run `git fetch --all --prune`.
+///
+/// Like every git command here, git runs in its own process group, so a
+/// terminal Ctrl-C doesn't reach it. When `cancel` fires, the group is sent SIGTERM so git can remove its
+/// lock files, and killed only if it hasn't exited after
+/// `FETCH_STOP_GRACE`. Without `cancel` the fetch always runs to the end.
+pub async fn fetch_all(
+    repo_path: &Path,
+    kind: RepoKind,
+    cancel: Option<&CancellationToken>,
+) -> FetchOutcome {
+    let args = ["fetch", "--all", "--prune"];
+    let mut cmd = git_command(repo_path, kind);
+    cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            return FetchOutcome::Error(format!(
+                "failed to run git {:?} in {}: {}",
+                args,
+                repo_path.display(),
+                e
+            ))
+        }
+    };
+    let pid = child.id();
+    let _group = pid.map(FetchGroup::track);
+    let output = child.wait_with_output();
+    tokio::pin!(output);
+
+    let output = match cancel {
+        Some(cancel) => tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                signal_group(pid, Signal::Terminate);
+                if tokio::time::timeout(FETCH_STOP_GRACE, &mut output).await.is_err() {
+                    signal_group(pid, Signal::Kill);
+                }
+                return FetchOutcome::Cancelled;
+            }
+            output = &mut output => output,
+        },
+        None => output.await,
+    };
+
+    let result = output
+        .with_context(|| format!("failed to run git {:?} in {}", args, repo_path.display()))
+        .and_then(|output| check_output(output, repo_path, &args));
     match result {
         Ok((_, stderr)) => {
             let refs_updated = count_ref_updates(&stderr);
@@ -40,6 +107,34 @@ pub async fn fetch_all(repo_path: &Path, kind: RepoKind) -> FetchOutcome {
     }
 }
 
+enum Signal {
+    Terminate,
+    Kill,
+}
+
+/// Signal the process group led by `pid` (see `fetch_all`)
+#[cfg(unix)]
+fn signal_group(pid: Option<u32>, signal: Signal) {
+    let Some(pid) = pid else {
+        // Already reaped
+        return;
+    };
+    let signal = match signal {
+        Signal::Terminate => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // SAFETY: killpg has no memory-safety preconditions; at worst the group
+    // is gone and it fails with ESRCH
+    unsafe {
+        libc::killpg(pid as libc::pid_t, signal);
+    }
+}
+
+/// No process groups or graceful stop: the child is killed when the fetch
+/// future is dropped
+#[cfg(not(unix))]
+fn signal_group(_pid: Option<u32>, _signal: Signal) {}
+
 fn count_ref_updates(stderr: &str) -> usize {
     // git fetch prints lines like:
     //   abc1234..def5678  main       -> origin/main
@@ -58,6 +153,123 @@ fn count_ref_updates(stderr: &str) -> usize {
         .count()
 }
 
+/// Local branches with their upstream tracking info.
+///
+/// Branch and upstream commits are read in the same `for-each-ref` call, so
+/// they are a consistent snapshot to compare against later.
+pub async fn local_branches(repo_path: &Path, kind: RepoKind) -> Vec<LocalBranch> {
+    let format = "--format=%(refname)%09%(objectname)%09%(upstream)%09%(upstream:remotename)\
+                  %09%(upstream:track,nobracket)%09%(worktreepath)\
+                  %09%(push:remotename)%09%(push:remoteref)%09%(push)";
+    let args = ["for-each-ref", format, "refs/heads", "refs/remotes"];
+    let output = match git_cmd(repo_path, kind, &args).await {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+
+    // A bare repo's HEAD branch reports the repo itself as its worktree
+    let bare_path = (kind == RepoKind::Bare).then(|| canonical_path(repo_path));
+
+    // Upstreams are usually remote-tracking refs, but may be local branches
+    let oids: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some((fields.next()?, fields.next()?))
+        })
+        .collect();
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.strip_prefix("refs/heads/")?.to_string();
+            let oid = fields.next()?.to_string();
+            let non_empty = |s: Option<&str>| s.filter(|s| !s.is_empty()).map(str::to_string);
+            let upstream = non_empty(fields.next());
+            let remote = non_empty(fields.next());
+            let track = fields.next().unwrap_or("");
+            let upstream_gone = track == "gone";
+            let ahead_behind = if upstream.is_some() && !upstream_gone {
+                Some(parse_track(track))
+            } else {
+                None
+            };
+            let upstream_oid = upstream
+                .as_deref()
+                .and_then(|upstream| oids.get(upstream))
+                .map(|oid| oid.to_string());
+            Some(LocalBranch {
+                name,
+                oid,
+                upstream,
+                upstream_oid,
+                remote,
+                upstream_gone,
+                ahead_behind,
+                worktree: non_empty(fields.next())
+                    .map(PathBuf::from)
+                    .filter(|path| bare_path.as_ref() != Some(&canonical_path(path))),
+                push_remote: non_empty(fields.next()),
+                push_ref: non_empty(fields.next()),
+                push_tracking: non_empty(fields.next()),
+                pull_result: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse `%(upstream:track,nobracket)`, e.g. `ahead 2, behind 1`, into
+/// (ahead, behind). Empty means in sync.
+fn parse_track(track: &str) -> (usize, usize) {
+    let mut ahead = 0;
+    let mut behind = 0;
+    for part in track.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", n)) => ahead = n.parse().unwrap_or(0),
+            Some(("behind", n)) => behind = n.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (ahead, behind)
+}
+
+/// Each remote's default branch as a full ref, keyed by remote name.
+///
+/// Uses `refs/remotes/<remote>/HEAD`, which clones set but bare repos
+/// often lack; then falls back to `<remote>/main` or `<remote>/master`.
+pub async fn remote_default_branches(repo_path: &Path, kind: RepoKind) -> HashMap<String, String> {
+    let format = "--format=%(refname)%09%(symref)";
+    let output = match git_cmd(repo_path, kind, &["for-each-ref", format, "refs/remotes"]).await {
+        Ok(output) => output,
+        Err(_) => return HashMap::new(),
+    };
+
+    let mut heads = HashMap::new();
+    let mut fallbacks: HashMap<String, String> = HashMap::new();
+    for line in output.lines() {
+        let Some((refname, symref)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some(short) = refname.strip_prefix("refs/remotes/") else {
+            continue;
+        };
+        let Some((remote, branch)) = short.rsplit_once('/') else {
+            continue;
+        };
+        if branch == "HEAD" && !symref.is_empty() {
+            heads.insert(remote.to_string(), symref.to_string());
+        } else if branch == "main" || (branch == "master" && !fallbacks.contains_key(remote)) {
+            fallbacks.insert(remote.to_string(), refname.to_string());
+        }
+    }
+
+    for (remote, refname) in fallbacks {
+        heads.entry(remote).or_insert(refname);
+    }
+    heads
+}
+
 pub async fn list_worktrees(repo_path: &Path, kind: RepoKind) -> Result<Vec<WorktreeInfo>> {
     let output = git_cmd(repo_path, kind, &["worktree", "list", "--porcelain"]).await?;
     parse_worktree_porcelain(&output)
@@ -79,7 +291,16 @@ fn parse_worktree_porcelain(output: &str) -> Result<Vec<WorktreeInfo>> {
                     branch: current_branch.take(),
                     detached_head: if is_detached { detached_commit.take() } else { None },
                     ahead_behind: None,
+                    upstream: None,
+                    working_tree: None,
+                    operation: None,
+                    vs_default: None,
+                    vs_push: None,
                     pull_result: None,
+                    push: None,
+                    push_result: None,
+                    hooks: Vec::new(),
+                    stale_submodules: Vec::new(),
                 });
             }
             current_path = Some(std::path::PathBuf::from(path_str));
@@ -112,7 +333,16 @@ fn parse_worktree_porcelain(output: &str) -> Result<Vec<WorktreeInfo>> {
             branch: current_branch,
             detached_head: if is_detached { detached_commit } else { None },
             ahead_behind: None,
+            upstream: None,
+            working_tree: None,
+            operation: None,
+            vs_default: None,
+            vs_push: None,
             pull_result: None,
+            push: None,
+            push_result: None,
+            hooks: Vec::new(),
+            stale_submodules: Vec::new(),
         });
     }
 
@@ -120,9 +350,15 @@ fn parse_worktree_porcelain(output: &str) -> Result<Vec<WorktreeInfo>> {
 }
 
 pub async fn ahead_behind(worktree_path: &Path) -> Option<(usize, usize)> {
-    let result = Command::new("git")
+    ahead_behind_of(worktree_path, "HEAD@{upstream}").await
+}
+
+/// Commits HEAD has that `rev` doesn't (ahead) and vice versa (behind)
+pub async fn ahead_behind_of(worktree_path: &Path, rev: &str) -> Option<(usize, usize)> {
+    let range = format!("HEAD...{}", rev);
+    let result = git()
         .args(["-C", &worktree_path.to_string_lossy()])
-        .args(["rev-list", "--left-right", "--count", "HEAD...HEAD@{upstream}"])
+        .args(["rev-list", "--left-right", "--count", &range])
         .output()
         .await
         .ok()?;
@@ -142,13 +378,288 @@ pub async fn ahead_behind(worktree_path: &Path) -> Option<(usize, usize)> {
     }
 }
 
-pub async fn pull_ff_only(worktree_path: &Path) -> Result<()> {
-    let output = Command::new("git")
+/// Paths of submodules whose checked-out commit differs from the one the
+/// superproject records (`+` in `git submodule status`)
+pub async fn stale_submodules(worktree_path: &Path) -> Vec<String> {
+    // Skip the extra git call for the common case of no submodules
+    if !worktree_path.join(".gitmodules").exists() {
+        return Vec::new();
+    }
+    let output = match git_cmd(
+        worktree_path,
+        RepoKind::NonBare,
+        &["submodule", "status", "--recursive"],
+    )
+    .await
+    {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix('+'))
+        .filter_map(|rest| rest.split_whitespace().nth(1))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Point submodules at the URLs and commits the superproject now records
+pub async fn update_submodules(worktree_path: &Path) -> Result<()> {
+    git_cmd(
+        worktree_path,
+        RepoKind::NonBare,
+        &["submodule", "sync", "--recursive"],
+    )
+    .await?;
+    git_cmd(
+        worktree_path,
+        RepoKind::NonBare,
+        &["submodule", "update", "--init", "--recursive"],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn working_tree_status(worktree_path: &Path) -> Option<WorkingTreeStatus> {
+    let result = git()
+        .args(["-C", &worktree_path.to_string_lossy()])
+        .args(["status", "--porcelain=v2"])
+        .output()
+        .await
+        .ok()?;
+
+    if !result.status.success() {
+        return None;
+    }
+
+    Some(parse_status_porcelain_v2(&String::from_utf8_lossy(
+        &result.stdout,
+    )))
+}
+
+fn parse_status_porcelain_v2(output: &str) -> WorkingTreeStatus {
+    // Entries look like:
+    //   1 .M N... 100644 100644 100644 <oid> <oid> path      (ordinary change)
+    //   2 R. N... ... path\torig                             (rename/copy)
+    //   u UU N... ... path                                  (unmerged)
+    //   ? path                                              (untracked)
+    // where XY is the index (staged) and worktree (unstaged) state, and the
+    // third field is `N...` for files or `S<c><m><u>` for submodules
+    // (commit changed, modified content, untracked content).
+    let mut status = WorkingTreeStatus::default();
+    for line in output.lines() {
+        let mut fields = line.splitn(4, ' ');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("1" | "2"), Some(xy), sub) => {
+                let mut xy = xy.chars();
+                if xy.next().is_some_and(|x| x != '.') {
+                    status.staged += 1;
+                }
+                if xy.next().is_some_and(|y| y != '.') {
+                    status.unstaged += 1;
+                    if sub == Some("SC..") {
+                        status.submodule_pointers += 1;
+                    }
+                }
+            }
+            (Some("u"), _, _) => status.conflicted += 1,
+            (Some("?"), _, _) => status.untracked += 1,
+            _ => {}
+        }
+    }
+    status
+}
+
+/// Detect a rebase, am, merge, cherry-pick, revert or bisect in progress
+pub async fn operation_in_progress(worktree_path: &Path) -> Option<GitOperation> {
+    // Linked worktrees keep this state in their own git dir
+    // (`<repo>/worktrees/<name>`), so ask git where that is
+    let result = git()
         .args(["-C", &worktree_path.to_string_lossy()])
-        .args(["pull", "--ff-only"])
+        .args(["rev-parse", "--absolute-git-dir"])
         .output()
         .await
-        .context("failed to run git pull")?;
+        .ok()?;
+
+    if !result.status.success() {
+        return None;
+    }
+
+    let git_dir = PathBuf::from(String::from_utf8_lossy(&result.stdout).trim());
+    if git_dir.join("rebase-merge").is_dir() {
+        Some(GitOperation::Rebase)
+    } else if git_dir.join("rebase-apply").is_dir() {
+        if git_dir.join("rebase-apply").join("applying").exists() {
+            Some(GitOperation::Am)
+        } else {
+            Some(GitOperation::Rebase)
+        }
+    } else if git_dir.join("MERGE_HEAD").exists() {
+        Some(GitOperation::Merge)
+    } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+        Some(GitOperation::CherryPick)
+    } else if git_dir.join("REVERT_HEAD").exists() {
+        Some(GitOperation::Revert)
+    } else if git_dir.join("BISECT_LOG").exists() {
+        Some(GitOperation::Bisect)
+    } else {
+        None
+    }
+}
+
+/// Stash entries, newest first. Stashes live in the common ref store, so
+/// for bare repos this covers stashes made in any linked worktree.
+pub async fn list_stashes(repo_path: &Path, kind: RepoKind) -> Vec<StashEntry> {
+    // `git stash list` refuses to run without a work tree, but it is just
+    // the reflog of refs/stash, which `git log -g` reads anywhere
+    let args = ["log", "--walk-reflogs", "--format=%ct%x09%gs", "refs/stash"];
+    let output = match git_cmd(repo_path, kind, &args).await {
+        Ok(output) => output,
+        // No refs/stash: nothing stashed
+        Err(_) => return Vec::new(),
+    };
+
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .enumerate()
+        .filter_map(|(index, (timestamp, subject))| {
+            let secs: u64 = timestamp.parse().ok()?;
+            Some(StashEntry {
+                index,
+                branch: stash_branch(subject),
+                message: subject.to_string(),
+                created: UNIX_EPOCH + Duration::from_secs(secs),
+            })
+        })
+        .collect()
+}
+
+fn stash_branch(subject: &str) -> Option<String> {
+    // "WIP on main: abc1234 msg" or "On main: custom message"
+    let rest = subject
+        .strip_prefix("WIP on ")
+        .or_else(|| subject.strip_prefix("On "))?;
+    let (branch, _) = rest.split_once(':')?;
+    if branch == "(no branch)" {
+        None
+    } else {
+        Some(branch.to_string())
+    }
+}
+
+/// Resolve a revision to its full object id
+pub async fn rev_parse(repo_path: &Path, kind: RepoKind, rev: &str) -> Result<String> {
+    let output = git_cmd(repo_path, kind, &["rev-parse", "--verify", "--quiet", rev]).await?;
+    Ok(output.trim().to_string())
+}
+
+/// Whether `ancestor` is reachable from `descendant`
+pub async fn is_ancestor(
+    repo_path: &Path,
+    kind: RepoKind,
+    ancestor: &str,
+    descendant: &str,
+) -> Result<bool> {
+    let status = git_command(repo_path, kind)
+        .args(["merge-base", "--is-ancestor", ancestor, descendant])
+        .status()
+        .await
+        .context("failed to run git merge-base")?;
+
+    match status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => anyhow::bail!(
+            "git merge-base --is-ancestor failed in {}",
+            repo_path.display()
+        ),
+    }
+}
+
+/// Move `refname` from `old` to `new`; git refuses if it no longer points at `old`
+pub async fn update_ref(
+    repo_path: &Path,
+    kind: RepoKind,
+    refname: &str,
+    new: &str,
+    old: &str,
+    reason: &str,
+) -> Result<()> {
+    let message = format!("git-worktree-refresh: {}", reason);
+    git_cmd(
+        repo_path,
+        kind,
+        &["update-ref", "-m", &message, refname, new, old],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Full ref name HEAD points at in a worktree; fails if HEAD is detached
+pub async fn symbolic_head(worktree_path: &Path) -> Result<String> {
+    let output = git_cmd(worktree_path, RepoKind::NonBare, &["symbolic-ref", "HEAD"]).await?;
+    Ok(output.trim().to_string())
+}
+
+/// Move the checked-out branch to `target`, keeping local changes; git
+/// refuses if a changed file would have to be overwritten
+pub async fn reset_keep(worktree_path: &Path, target: &str) -> Result<()> {
+    git_cmd(
+        worktree_path,
+        RepoKind::NonBare,
+        &["reset", "--keep", target],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Rebase the worktree's branch onto `target` (an already-fetched commit)
+pub async fn rebase(worktree_path: &Path, target: &str) -> Result<()> {
+    git_cmd(worktree_path, RepoKind::NonBare, &["rebase", target]).await?;
+    Ok(())
+}
+
+pub async fn rebase_abort(worktree_path: &Path) -> Result<()> {
+    git_cmd(worktree_path, RepoKind::NonBare, &["rebase", "--abort"]).await?;
+    Ok(())
+}
+
+/// Push a local branch to `remote_ref` on `remote`; with `dry_run`, git
+/// checks the push (including with the remote) without updating anything
+pub async fn push(
+    worktree_path: &Path,
+    remote: &str,
+    branch: &str,
+    remote_ref: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let refspec = format!("refs/heads/{}:{}", branch, remote_ref);
+    let mut args = vec!["push"];
+    if dry_run {
+        args.push("--dry-run");
+    }
+    args.extend([remote, refspec.as_str()]);
+    git_cmd(worktree_path, RepoKind::NonBare, &args).await?;
+    Ok(())
+}
+
+/// Fast-forward the worktree's branch to `target` (an already-fetched
+/// commit), without touching the network. With `autostash`, local changes
+/// are stashed before and restored after.
+pub async fn merge_ff_only(worktree_path: &Path, target: &str, autostash: bool) -> Result<()> {
+    let mut cmd = git();
+    cmd.args(["-C", &worktree_path.to_string_lossy()])
+        .args(["merge", "--ff-only"]);
+    if autostash {
+        cmd.arg("--autostash");
+    }
+    let output = cmd
+        .arg(target)
+        .output()
+        .await
+        .context("failed to run git merge")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -167,7 +678,11 @@ async fn git_cmd_full(
     kind: RepoKind,
     args: &[&str],
 ) -> Result<(String, String)> {
-    let mut cmd = Command::new("git");
+    run_git(git_command(repo_path, kind), repo_path, args).await
+}
+
+fn git_command(repo_path: &Path, kind: RepoKind) -> Command {
+    let mut cmd = git();
 
     match kind {
         RepoKind::Bare => {
@@ -178,13 +693,35 @@ async fn git_cmd_full(
         }
     }
 
+    // Dropping the future (e.g. on cancellation) terminates the child
+    cmd.kill_on_drop(true);
+    cmd
+}
+
+/// A git command in its own process group.
+///
+/// A terminal Ctrl-C only cancels the run (see `interrupt`): status queries
+/// of repos that already finished fetching have to complete for their
+/// partial results, and fetches are stopped by `fetch_all` itself.
+fn git() -> Command {
+    let mut cmd = Command::new("git");
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+async fn run_git(mut cmd: Command, repo_path: &Path, args: &[&str]) -> Result<(String, String)> {
     cmd.args(args);
 
     let output = cmd
         .output()
         .await
         .with_context(|| format!("failed to run git {:?} in {}", args, repo_path.display()))?;
+    check_output(output, repo_path, args)
+}
 
+/// Stdout and stderr of a finished git command, or an error if it failed
+fn check_output(output: Output, repo_path: &Path, args: &[&str]) -> Result<(String, String)> {
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!(
@@ -200,3 +737,65 @@ async fn git_cmd_full(
         String::from_utf8_lossy(&output.stderr).to_string(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {:?}", args, output);
+    }
+
+    #[tokio::test]
+    async fn bare_repo_head_branch_is_not_checked_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let seed = dir.path().join("seed");
+        git(dir.path(), &["init", "-q", "-b", "main", "seed"]);
+        git(&seed, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&seed, &["branch", "feature"]);
+        git(dir.path(), &["clone", "-q", "--bare", "seed", "proj.git"]);
+        let bare = dir.path().join("proj.git");
+        git(&bare, &["worktree", "add", "-q", "../feature", "feature"]);
+
+        let branches = local_branches(&bare, RepoKind::Bare).await;
+        let worktree = |name: &str| {
+            let branch = branches.iter().find(|b| b.name == name).unwrap();
+            branch.worktree.as_deref().map(canonical_path)
+        };
+        assert_eq!(worktree("main"), None);
+        assert_eq!(
+            worktree("feature"),
+            Some(canonical_path(&dir.path().join("feature")))
+        );
+    }
+
+    #[test]
+    fn porcelain_v2_counts_submodule_pointer_changes_separately() {
+        let output = "\
+1 .M SC.. 160000 160000 160000 aaa aaa sub
+1 .M S.M. 160000 160000 160000 bbb bbb other
+1 M. N... 100644 100644 100644 ccc ddd staged.txt
+1 .M N... 100644 100644 100644 eee eee edited.txt
+? new.txt
+";
+        let status = parse_status_porcelain_v2(output);
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.unstaged, 3);
+        assert_eq!(status.untracked, 1);
+        assert_eq!(status.submodule_pointers, 1);
+    }
+
+    #[test]
+    fn only_submodule_pointers_is_not_dirty_besides_them() {
+        let status = parse_status_porcelain_v2("1 .M SC.. 160000 160000 160000 aaa aaa sub\n");
+        assert!(status.is_dirty());
+        assert!(!status.is_dirty_besides_submodule_pointers());
+    }
+}
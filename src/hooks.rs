@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+use crate::policy::{canonical_path, glob_match};
+use crate::types::{AppConfig, HookOutcome, PostPullHook};
+
+/// Runs the configured `post_pull` commands, bounded by `hook_concurrency`
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    hooks: Arc<Vec<PostPullHook>>,
+    limit: Arc<Semaphore>,
+}
+
+/// A worktree that auto-pull moved from `old` to `new`
+pub struct PulledWorktree {
+    pub repo: PathBuf,
+    pub worktree: PathBuf,
+    pub branch: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl Hooks {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            hooks: Arc::new(config.post_pull.clone()),
+            limit: Arc::new(Semaphore::new(config.hook_concurrency.max(1))),
+        }
+    }
+
+    /// Run every hook matching the repo in the pulled worktree, in config
+    /// order, stopping at the first failure.
+    pub async fn run_post_pull(&self, pulled: &PulledWorktree) -> Vec<HookOutcome> {
+        let repo = canonical_path(&pulled.repo);
+        let repo = repo.to_string_lossy();
+        let matching: Vec<_> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.repos.is_empty() || hook.repos.iter().any(|g| glob_match(g, &repo)))
+            .collect();
+        if matching.is_empty() {
+            return Vec::new();
+        }
+
+        let _permit = self.limit.acquire().await.unwrap();
+        let mut outcomes = Vec::new();
+        for hook in matching {
+            let outcome = run_hook(&hook.run, pulled).await;
+            let failed = !outcome.success;
+            outcomes.push(outcome);
+            if failed {
+                break;
+            }
+        }
+        outcomes
+    }
+}
+
+async fn run_hook(command: &str, pulled: &PulledWorktree) -> HookOutcome {
+    let result = shell(command, &pulled.worktree)
+        .env("GWR_REPO", &pulled.repo)
+        .env("GWR_WORKTREE", &pulled.worktree)
+        .env("GWR_BRANCH", &pulled.branch)
+        .env("GWR_OLD_OID", &pulled.old)
+        .env("GWR_NEW_OID", &pulled.new)
+        .stdin(Stdio::null())
+        .output()
+        .await;
+
+    match result {
+        Ok(output) => {
+            // Interleaving is lost, but both streams are kept
+            let mut captured = String::from_utf8_lossy(&output.stdout).to_string();
+            captured.push_str(&String::from_utf8_lossy(&output.stderr));
+            HookOutcome {
+                command: command.to_string(),
+                success: output.status.success(),
+                exit_code: output.status.code(),
+                output: captured,
+            }
+        }
+        Err(e) => HookOutcome {
+            command: command.to_string(),
+            success: false,
+            exit_code: None,
+            output: format!("failed to run hook: {}", e),
+        },
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str, dir: &Path) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).current_dir(dir);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(command: &str, dir: &Path) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command).current_dir(dir);
+    cmd
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use tokio_util::sync::CancellationToken;
+
+/// Exit code after an interrupted run (128 + SIGINT, as shells report it)
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Process groups of running fetches, stopped before a hard exit
+static FETCH_GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Install the Ctrl-C handler.
+///
+/// The first Ctrl-C cancels the returned token so in-progress work can wind
+/// down and report partial results; a second one stops the fetches still
+/// running and exits immediately.
+pub fn install() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        handler_token.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            terminate_fetches();
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+
+    token
+}
+
+/// A running fetch's process group, tracked until dropped.
+///
+/// Fetches don't get the terminal's SIGINT (see `git::fetch_all`), so
+/// without this they would outlive a hard exit.
+pub struct FetchGroup(u32);
+
+impl FetchGroup {
+    pub fn track(pgid: u32) -> Self {
+        let mut groups = FETCH_GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+        groups.insert(pgid);
+        FetchGroup(pgid)
+    }
+}
+
+impl Drop for FetchGroup {
+    fn drop(&mut self) {
+        let mut groups = FETCH_GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+        groups.remove(&self.0);
+    }
+}
+
+/// Send SIGTERM to every tracked fetch, so git still removes its lock files
+#[cfg(unix)]
+fn terminate_fetches() {
+    let groups = FETCH_GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+    for &pgid in groups.iter() {
+        // SAFETY: killpg has no memory-safety preconditions; at worst the
+        // group is gone and it fails with ESRCH
+        unsafe {
+            libc::killpg(pgid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+}
+
+/// Without process groups the children are in the terminal's group, and
+/// get its Ctrl-C themselves
+#[cfg(not(unix))]
+fn terminate_fetches() {}
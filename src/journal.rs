@@ -0,0 +1,215 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use crate::config;
+use crate::types::RepoKind;
+
+/// A ref moved by the tool
+#[derive(Debug, Clone)]
+pub struct RefChange {
+    pub repo: PathBuf,
+    pub kind: RepoKind,
+    /// Worktree the ref is checked out in; `None` for a plain ref update
+    pub worktree: Option<PathBuf>,
+    /// Full ref name, e.g. `refs/heads/main`
+    pub refname: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    /// Run that made the change (its start time in milliseconds)
+    pub run: u64,
+    /// Unix time of the change
+    pub timestamp: u64,
+    pub change: RefChange,
+}
+
+/// Append-only log of every ref movement, so a run can be undone.
+///
+/// One tab-separated line per change:
+/// `run  timestamp  kind  repo  worktree  ref  old  new`.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: Option<PathBuf>,
+    run: u64,
+}
+
+impl Journal {
+    /// Start a new run; nothing is written until a change is recorded
+    pub fn open() -> Self {
+        let run = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            path: journal_path(),
+            run,
+        }
+    }
+
+    /// A run journaled to `path` instead of the state directory
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::open()
+        }
+    }
+
+    pub fn record(&self, change: RefChange) {
+        let Some(path) = &self.path else {
+            eprintln!("Warning: no state directory, ref change not journaled");
+            return;
+        };
+        let entry = JournalEntry {
+            run: self.run,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            change,
+        };
+        if let Err(e) = append(path, &entry) {
+            eprintln!("Warning: failed to write journal {}: {}", path.display(), e);
+        }
+    }
+}
+
+pub fn journal_path() -> Option<PathBuf> {
+    Some(config::state_dir()?.join("journal.tsv"))
+}
+
+fn append(path: &Path, entry: &JournalEntry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let change = &entry.change;
+    let kind = match change.kind {
+        RepoKind::Bare => "bare",
+        RepoKind::NonBare => "non-bare",
+    };
+    let worktree = change
+        .worktree
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let line = format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        entry.run,
+        entry.timestamp,
+        kind,
+        change.repo.display(),
+        worktree,
+        change.refname,
+        change.old,
+        change.new
+    );
+
+    // A single write per line keeps concurrent appends from interleaving
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// All journaled changes, oldest first; malformed lines are skipped
+pub fn read(path: &Path) -> Result<Vec<JournalEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to read journal {}", path.display()))
+        }
+    };
+
+    Ok(content.lines().filter_map(parse_line).collect())
+}
+
+fn parse_line(line: &str) -> Option<JournalEntry> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [run, timestamp, kind, repo, worktree, refname, old, new] = fields[..] else {
+        return None;
+    };
+    let kind = match kind {
+        "bare" => RepoKind::Bare,
+        "non-bare" => RepoKind::NonBare,
+        _ => return None,
+    };
+    Some(JournalEntry {
+        run: run.parse().ok()?,
+        timestamp: timestamp.parse().ok()?,
+        change: RefChange {
+            repo: PathBuf::from(repo),
+            kind,
+            worktree: (!worktree.is_empty()).then(|| PathBuf::from(worktree)),
+            refname: refname.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(run: u64, worktree: Option<&str>) -> JournalEntry {
+        JournalEntry {
+            run,
+            timestamp: 1_700_000_000,
+            change: RefChange {
+                repo: PathBuf::from("/code/my repo"),
+                kind: RepoKind::NonBare,
+                worktree: worktree.map(PathBuf::from),
+                refname: "refs/heads/main".to_string(),
+                old: "a".repeat(40),
+                new: "b".repeat(40),
+            },
+        }
+    }
+
+    #[test]
+    fn appended_entries_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("journal.tsv");
+        append(&path, &entry(1, None)).unwrap();
+        append(&path, &entry(2, Some("/code/my repo/wt"))).unwrap();
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        let (first, second) = (&entries[0], &entries[1]);
+        assert_eq!(first.run, 1);
+        assert_eq!(first.timestamp, 1_700_000_000);
+        assert_eq!(first.change.repo, Path::new("/code/my repo"));
+        assert_eq!(first.change.kind, RepoKind::NonBare);
+        assert_eq!(first.change.worktree, None);
+        assert_eq!(first.change.refname, "refs/heads/main");
+        assert_eq!(first.change.old, "a".repeat(40));
+        assert_eq!(first.change.new, "b".repeat(40));
+        assert_eq!(second.run, 2);
+        assert_eq!(
+            second.change.worktree.as_deref(),
+            Some(Path::new("/code/my repo/wt"))
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        assert!(parse_line("1\t2\tnon-bare\t/repo").is_none());
+        assert!(parse_line("1\t2\tweird\t/repo\t\trefs/heads/main\ta\tb").is_none());
+        assert!(parse_line("x\t2\tbare\t/repo\t\trefs/heads/main\ta\tb").is_none());
+        let entry = parse_line("1\t2\tbare\t/repo\t\trefs/heads/main\ta\tb").unwrap();
+        assert_eq!(entry.change.kind, RepoKind::Bare);
+    }
+
+    #[test]
+    fn missing_journal_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read(&dir.path().join("journal.tsv")).unwrap().is_empty());
+    }
+}
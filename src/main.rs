@@ -1,20 +1,38 @@
 mod autopull;
+mod autopush;
 mod cli;
 mod config;
 mod discovery;
 mod fetch;
+mod fetch_times;
+mod filter;
 mod git;
+mod hooks;
+mod interrupt;
+mod journal;
 mod output;
+mod pipeline;
+mod policy;
+mod progress;
+mod report;
 mod status;
 mod types;
+mod undo;
 
 use anyhow::Result;
 use clap::Parser;
-use types::{FetchOutcome, FetchResult};
+
+use crate::types::OutputFormat;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = cli::Cli::parse();
+
+    if let Some(cli::Command::Undo) = cli.command {
+        git::check_git_available().await?;
+        return undo::undo_last_run().await;
+    }
+
     let config = config::resolve_config(&cli)?;
 
     // Ensure git is available
@@ -22,34 +40,29 @@ async fn main() -> Result<()> {
 
     // Discover repos
     let repos = discovery::discover_repos(&config.directories, config.max_depth);
-    if repos.is_empty() {
+    // Machine-readable formats still report an empty run
+    if repos.is_empty() && config.format == OutputFormat::Text {
         println!("No git repositories found in configured directories.");
         return Ok(());
     }
 
-    let fetch_results = if config.fetch {
-        // Fetch all repos in parallel
-        fetch::fetch_all_repos(repos, config.concurrency).await
-    } else {
-        repos
-            .into_iter()
-            .map(|repo| FetchResult {
-                repo,
-                outcome: FetchOutcome::Skipped,
-            })
-            .collect()
-    };
-
-    // Build status with worktree info
-    let mut statuses = status::build_repo_statuses(fetch_results).await;
-
-    // Auto-pull if enabled
-    if config.auto_pull {
-        autopull::auto_pull_eligible(&mut statuses).await;
+    let cancel = interrupt::install();
+
+    // Fetch, gather status and print each repo as it completes
+    let statuses = pipeline::run(repos, &config, &cancel).await;
+
+    match config.format {
+        OutputFormat::Text => output::print_summary(&statuses, &config),
+        OutputFormat::Json => report::print_json(&statuses, &config, cancel.is_cancelled())?,
+        OutputFormat::Ndjson => {
+            let summary = report::summary(&statuses, cancel.is_cancelled());
+            report::print_event(&report::Event::Summary(summary));
+        }
     }
 
-    // Print results
-    output::print_results(&statuses, config.emoji, config.show_all);
+    if cancel.is_cancelled() {
+        std::process::exit(interrupt::EXIT_INTERRUPTED);
+    }
 
     Ok(())
 }
@@ -1,38 +1,109 @@
+use std::time::{Duration, SystemTime};
+
 use owo_colors::OwoColorize;
 use owo_colors::Stream::Stdout;
 
-use crate::types::{FetchOutcome, PullResult, RepoKind, RepoStatus};
+use crate::filter;
+use crate::types::{
+    AppConfig, FetchOutcome, HookOutcome, LocalBranch, PullResult, PushResult, RefComparison,
+    RepoKind, RepoStatus, SkipReason, StashEntry, WorkingTreeStatus, WorktreeInfo,
+};
+
+/// Lines of a failed hook's output to show
+const HOOK_OUTPUT_LINES: usize = 10;
 
 fn has_worktree_changes(status: &RepoStatus) -> bool {
-    status
-        .worktrees
-        .iter()
-        .any(|wt| matches!(wt.ahead_behind, Some((a, b)) if a > 0 || b > 0))
+    status.worktrees.iter().any(|wt| {
+        matches!(wt.ahead_behind, Some((a, b)) if a > 0 || b > 0)
+            || wt.pull_result.is_some()
+            || wt.push_result.is_some()
+    })
+}
+
+fn has_branch_changes(status: &RepoStatus) -> bool {
+    status.branches.iter().any(|b| {
+        matches!(b.ahead_behind, Some((a, b)) if a > 0 || b > 0) || b.pull_result.is_some()
+    })
+}
+
+/// Fetched new refs, or a worktree or branch is ahead/behind, pulled or pushed
+pub fn has_changes(status: &RepoStatus) -> bool {
+    matches!(status.fetch_outcome, FetchOutcome::Updated { .. })
+        || has_worktree_changes(status)
+        || has_branch_changes(status)
+}
+
+/// Worktrees selected by `--filter` and `--stashes-older-than`, or `None`
+/// if they exclude the whole repo.
+///
+/// `--stashes-older-than` drops repos without such a stash. Worktree
+/// filters keep only matching worktrees and drop repos without any.
+pub fn selected_worktrees<'a>(
+    status: &'a RepoStatus,
+    config: &AppConfig,
+) -> Option<Vec<&'a WorktreeInfo>> {
+    if let Some(min_age) = config.stashes_older_than {
+        if !filter::has_stash_older_than(status, min_age) {
+            return None;
+        }
+    }
+
+    if !config.filters.is_empty() {
+        let matching = filter::matching_worktrees(&config.filters, status);
+        return (!matching.is_empty()).then_some(matching);
+    }
+    Some(status.worktrees.iter().collect())
+}
+
+/// Worktrees to print for a repo, or `None` if the whole repo is hidden.
+///
+/// On top of [`selected_worktrees`], repos without changes are hidden
+/// unless `show_all` or a filter is given.
+fn visible_worktrees<'a>(
+    status: &'a RepoStatus,
+    config: &AppConfig,
+) -> Option<Vec<&'a WorktreeInfo>> {
+    let selected = selected_worktrees(status, config)?;
+    if !config.filters.is_empty() || config.stashes_older_than.is_some() {
+        return Some(selected);
+    }
+
+    let shown = config.show_all
+        || has_changes(status)
+        || matches!(status.fetch_outcome, FetchOutcome::Error(_))
+        || status.worktrees.iter().any(|wt| {
+            wt.operation.is_some() || wt.upstream_gone() || !wt.stale_submodules.is_empty()
+        })
+        || status.branches.iter().any(|b| b.upstream_gone);
+    shown.then_some(selected)
+}
+
+/// Print one repo's block, unless it is hidden by `show_all` or filters.
+pub fn print_repo_status(status: &RepoStatus, config: &AppConfig) {
+    if let Some(worktrees) = visible_worktrees(status, config) {
+        print_repo(status, &worktrees, config);
+    }
 }
 
-pub fn print_results(statuses: &[RepoStatus], emoji: bool, show_all: bool) {
+pub fn print_summary(statuses: &[RepoStatus], config: &AppConfig) {
+    let emoji = config.emoji;
     let mut shown = 0;
     let mut with_changes = 0;
     let mut errors = 0;
+    let mut cancelled = 0;
 
     for status in statuses {
-        let has_fetch_changes = matches!(status.fetch_outcome, FetchOutcome::Updated { .. });
-        let has_wt_changes = has_worktree_changes(status);
-        let has_error = matches!(status.fetch_outcome, FetchOutcome::Error(_));
-
-        if has_fetch_changes || has_wt_changes {
+        if has_changes(status) {
             with_changes += 1;
         }
-        if has_error {
-            errors += 1;
+        match status.fetch_outcome {
+            FetchOutcome::Error(_) => errors += 1,
+            FetchOutcome::Cancelled => cancelled += 1,
+            _ => {}
         }
-
-        if !show_all && !has_fetch_changes && !has_wt_changes && !has_error {
-            continue;
+        if visible_worktrees(status, config).is_some() {
+            shown += 1;
         }
-
-        print_repo(status, emoji);
-        shown += 1;
     }
 
     if shown > 0 {
@@ -42,8 +113,16 @@ pub fn print_results(statuses: &[RepoStatus], emoji: bool, show_all: bool) {
     // Summary line
     let total = statuses.len();
     let summary_prefix = if emoji { "📊 " } else { "" };
+    let cancelled_str = if cancelled > 0 {
+        format!(
+            ", {} cancelled",
+            cancelled.if_supports_color(Stdout, |t| t.yellow())
+        )
+    } else {
+        String::new()
+    };
     println!(
-        "{}Scanned {} repo(s): {} with changes, {} error(s)",
+        "{}Scanned {} repo(s): {} with changes, {} error(s){}",
         summary_prefix,
         total.if_supports_color(Stdout, |t| t.bold()),
         with_changes.if_supports_color(Stdout, |t| t.green()),
@@ -51,17 +130,28 @@ pub fn print_results(statuses: &[RepoStatus], emoji: bool, show_all: bool) {
             t.red().to_string()
         } else {
             t.to_string()
-        })
+        }),
+        cancelled_str
     );
 }
 
-fn print_repo(status: &RepoStatus, emoji: bool) {
-    let repo_name = status
-        .repo
-        .path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| status.repo.path.display().to_string());
+/// Compact age like `45s`, `3m`, `2h` or `5d`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m", secs / 60)
+    } else if secs < 24 * 60 * 60 {
+        format!("{}h", secs / (60 * 60))
+    } else {
+        format!("{}d", secs / (24 * 60 * 60))
+    }
+}
+
+fn print_repo(status: &RepoStatus, worktrees: &[&WorktreeInfo], config: &AppConfig) {
+    let emoji = config.emoji;
+    let repo_name = status.repo.name();
 
     let kind_str = match status.repo.kind {
         RepoKind::Bare => " (bare)",
@@ -88,7 +178,14 @@ fn print_repo(status: &RepoStatus, emoji: bool) {
         }
         FetchOutcome::NoChanges => " (no changes)".to_string(),
         FetchOutcome::NoRemote => " (no remote)".to_string(),
-        FetchOutcome::Skipped => " (fetch skipped)".to_string(),
+        FetchOutcome::Skipped(SkipReason::Disabled) => " (fetch skipped)".to_string(),
+        FetchOutcome::Skipped(SkipReason::FetchedRecently(age)) => {
+            format!(" (fetch skipped, fetched {} ago)", format_age(*age))
+        }
+        FetchOutcome::Cancelled => format!(
+            " {}",
+            "(cancelled)".if_supports_color(Stdout, |t| t.yellow())
+        ),
         FetchOutcome::Error(e) => format!(
             " {}",
             format!("error: {}", e).if_supports_color(Stdout, |t| t.red())
@@ -103,12 +200,73 @@ fn print_repo(status: &RepoStatus, emoji: bool) {
         fetch_info
     );
 
-    for wt in &status.worktrees {
+    if !status.stashes.is_empty() {
+        print_stashes(&status.stashes, emoji, config.stashes_older_than);
+    }
+
+    for wt in worktrees {
         print_worktree(wt, emoji);
     }
+
+    if !status.branches.is_empty() {
+        println!(
+            "  {}",
+            "branches:".if_supports_color(Stdout, |t| t.dimmed())
+        );
+        for branch in &status.branches {
+            print_branch(branch, emoji);
+        }
+    }
+}
+
+/// Summarize stashes on one line; with `list_older_than`, also list each
+/// stash older than that on its own line.
+fn print_stashes(stashes: &[StashEntry], emoji: bool, list_older_than: Option<Duration>) {
+    let now = SystemTime::now();
+    let age = |stash: &StashEntry| now.duration_since(stash.created).unwrap_or_default();
+    // Stashes are listed newest first
+    let newest = stashes.first().map(age).unwrap_or_default();
+    let oldest = stashes.last().map(age).unwrap_or_default();
+
+    let mut branches: Vec<&str> = Vec::new();
+    for stash in stashes {
+        let branch = stash.branch.as_deref().unwrap_or("(detached)");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+    }
+
+    let ages = if stashes.len() == 1 {
+        format!("{} old", format_age(newest))
+    } else {
+        format!(
+            "newest {}, oldest {}",
+            format_age(newest),
+            format_age(oldest)
+        )
+    };
+    let prefix = if emoji { "📚 " } else { "" };
+    println!(
+        "  {}{} stash(es), {} (on {})",
+        prefix,
+        stashes.len().if_supports_color(Stdout, |t| t.yellow()),
+        ages,
+        branches.join(", ")
+    );
+
+    if let Some(min_age) = list_older_than {
+        for stash in stashes.iter().filter(|s| age(s) > min_age) {
+            println!(
+                "    stash@{{{}}} {} {}",
+                stash.index,
+                format_age(age(stash)).if_supports_color(Stdout, |t| t.yellow()),
+                stash.message
+            );
+        }
+    }
 }
 
-fn print_worktree(wt: &crate::types::WorktreeInfo, emoji: bool) {
+fn print_worktree(wt: &WorktreeInfo, emoji: bool) {
     let wt_name = wt
         .path
         .file_name()
@@ -138,29 +296,16 @@ fn print_worktree(wt: &crate::types::WorktreeInfo, emoji: bool) {
                     .to_string()
             }
         }
-        Some((ahead, behind)) => {
-            let mut parts = Vec::new();
-            if behind > 0 {
-                let arrow = if emoji { "⬇️" } else { "v" };
-                parts.push(format!(
-                    "{} {}",
-                    arrow,
-                    behind.if_supports_color(Stdout, |t| t.red())
-                ));
-            }
-            if ahead > 0 {
-                let arrow = if emoji { "⬆️" } else { "^" };
-                parts.push(format!(
-                    "{} {}",
-                    arrow,
-                    ahead.if_supports_color(Stdout, |t| t.green())
-                ));
-            }
-            format!(" {}", parts.join(" "))
-        }
+        Some((ahead, behind)) => format!(" {}", format_arrows(ahead, behind, emoji)),
         None => {
             if wt.detached_head.is_some() {
                 String::new()
+            } else if let Some(upstream) = wt.upstream.as_ref().filter(|u| u.gone) {
+                format!(
+                    " {}",
+                    format!("(upstream gone: {})", upstream.name)
+                        .if_supports_color(Stdout, |t| t.yellow())
+                )
             } else if wt.branch.is_some() {
                 " (no upstream)".to_string()
             } else {
@@ -169,7 +314,135 @@ fn print_worktree(wt: &crate::types::WorktreeInfo, emoji: bool) {
         }
     };
 
-    let pull_str = match &wt.pull_result {
+    let cleanup_str = if wt.merged_and_gone() {
+        let base = wt.vs_default.as_ref().map_or("", |cmp| cmp.name.as_str());
+        let marker = if emoji { "🧹 " } else { "" };
+        format!(
+            " {}{}",
+            marker,
+            format!("merged into {}, safe to remove", base)
+                .if_supports_color(Stdout, |t| t.green())
+        )
+    } else {
+        String::new()
+    };
+
+    // Extra columns: position relative to the push destination (when it
+    // isn't the upstream) and to the remote's default branch
+    let push_str = wt
+        .vs_push
+        .as_ref()
+        .map(|cmp| format_comparison("push ", cmp, emoji))
+        .unwrap_or_default();
+    let default_str = wt
+        .vs_default
+        .as_ref()
+        .map(|cmp| format_comparison("", cmp, emoji))
+        .unwrap_or_default();
+
+    let operation_str = match wt.operation {
+        Some(op) => {
+            let label = format!("{} in progress", op.label().to_uppercase());
+            let marker = if emoji { "⚠️ " } else { "" };
+            format!(
+                " {}{}",
+                marker,
+                label.if_supports_color(Stdout, |t| t.bright_red().bold().to_string())
+            )
+        }
+        None => String::new(),
+    };
+
+    let submodules_str = if wt.stale_submodules.is_empty() {
+        String::new()
+    } else {
+        let marker = if emoji { "📎 " } else { "" };
+        format!(
+            " {}{}",
+            marker,
+            format!("submodules out of sync: {}", wt.stale_submodules.join(", "))
+                .if_supports_color(Stdout, |t| t.yellow())
+        )
+    };
+
+    let changes_str = match wt.working_tree {
+        Some(tree) if tree.is_dirty() => format_working_tree(&tree, emoji),
+        _ => String::new(),
+    };
+
+    let pull_str = format_pull_result(wt.pull_result.as_ref(), emoji);
+
+    let push_result_str = match &wt.push_result {
+        Some(PushResult::Pushed) => {
+            if emoji {
+                " 🚀 pushed"
+                    .if_supports_color(Stdout, |t| t.green())
+                    .to_string()
+            } else {
+                " (pushed)"
+                    .if_supports_color(Stdout, |t| t.green())
+                    .to_string()
+            }
+        }
+        Some(PushResult::WouldPush) => format!(
+            " {}",
+            "(would push)".if_supports_color(Stdout, |t| t.dimmed())
+        ),
+        Some(PushResult::PushFailed(e)) => {
+            format!(" push failed: {}", e.if_supports_color(Stdout, |t| t.red()))
+        }
+        None => String::new(),
+    };
+
+    println!(
+        "  {} {}{}{}{}{}{}{}{}{}{}",
+        wt_name.if_supports_color(Stdout, |t| t.cyan()),
+        branch_display,
+        operation_str,
+        status_str,
+        push_str,
+        default_str,
+        cleanup_str,
+        changes_str,
+        submodules_str,
+        pull_str,
+        push_result_str
+    );
+
+    for hook in &wt.hooks {
+        print_hook(hook, emoji);
+    }
+}
+
+/// Hook result under its worktree; failures include the tail of the output
+fn print_hook(hook: &HookOutcome, emoji: bool) {
+    let marker = if emoji { "🔧" } else { "->" };
+    let result = if hook.success {
+        "ok".if_supports_color(Stdout, |t| t.green()).to_string()
+    } else {
+        let reason = match hook.exit_code {
+            Some(code) => format!("failed (exit {})", code),
+            None => "failed".to_string(),
+        };
+        reason.if_supports_color(Stdout, |t| t.red()).to_string()
+    };
+    println!(
+        "    {} {} {}",
+        marker,
+        hook.command.if_supports_color(Stdout, |t| t.dimmed()),
+        result
+    );
+
+    if !hook.success {
+        let lines: Vec<&str> = hook.output.trim_end().lines().collect();
+        for line in &lines[lines.len().saturating_sub(HOOK_OUTPUT_LINES)..] {
+            println!("      {}", line);
+        }
+    }
+}
+
+fn format_pull_result(result: Option<&PullResult>, emoji: bool) -> String {
+    match result {
         Some(PullResult::Pulled) => {
             if emoji {
                 " ✨ pulled"
@@ -181,17 +454,147 @@ fn print_worktree(wt: &crate::types::WorktreeInfo, emoji: bool) {
                     .to_string()
             }
         }
+        Some(PullResult::Rebased) => {
+            if emoji {
+                " ✨ rebased"
+                    .if_supports_color(Stdout, |t| t.green())
+                    .to_string()
+            } else {
+                " (rebased)"
+                    .if_supports_color(Stdout, |t| t.green())
+                    .to_string()
+            }
+        }
+        Some(PullResult::RebaseAborted) => format!(
+            " {}",
+            "rebase conflicted, aborted".if_supports_color(Stdout, |t| t.red())
+        ),
+        Some(PullResult::NotAllowed) => format!(
+            " {}",
+            "(not allowed by policy)".if_supports_color(Stdout, |t| t.dimmed())
+        ),
+        Some(PullResult::Skipped(reason)) => format!(
+            " {}",
+            format!("(skipped: {})", reason).if_supports_color(Stdout, |t| t.yellow())
+        ),
         Some(PullResult::Failed(e)) => {
             format!(" pull failed: {}", e.if_supports_color(Stdout, |t| t.red()))
         }
         None => String::new(),
+    }
+}
+
+/// One line of the branch listing
+fn print_branch(branch: &LocalBranch, emoji: bool) {
+    let upstream = branch
+        .upstream
+        .as_deref()
+        .map(|u| u.strip_prefix("refs/remotes/").unwrap_or(u));
+
+    let status_str = match (branch.ahead_behind, upstream) {
+        (Some((0, 0)), Some(upstream)) => format!(
+            " {} {}",
+            upstream.if_supports_color(Stdout, |t| t.dimmed()),
+            "up to date".if_supports_color(Stdout, |t| t.green())
+        ),
+        (Some((ahead, behind)), Some(upstream)) => format!(
+            " {} {}",
+            upstream.if_supports_color(Stdout, |t| t.dimmed()),
+            format_arrows(ahead, behind, emoji)
+        ),
+        (None, Some(upstream)) if branch.upstream_gone => format!(
+            " {}",
+            format!("(upstream gone: {})", upstream).if_supports_color(Stdout, |t| t.yellow())
+        ),
+        _ => " (no upstream)".to_string(),
+    };
+
+    let checkout_str = match &branch.worktree {
+        Some(path) => {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            format!(
+                " (checked out in {})",
+                name.if_supports_color(Stdout, |t| t.cyan())
+            )
+        }
+        None => String::new(),
     };
 
     println!(
-        "  {} {}{}{}",
-        wt_name.if_supports_color(Stdout, |t| t.cyan()),
-        branch_display,
+        "    {}{}{}{}",
+        branch.name.if_supports_color(Stdout, |t| t.cyan()),
         status_str,
-        pull_str
+        checkout_str,
+        format_pull_result(branch.pull_result.as_ref(), emoji)
     );
 }
+
+/// ` [label origin/main: v N]`, or `even` when level
+fn format_comparison(label: &str, cmp: &RefComparison, emoji: bool) -> String {
+    let position = match cmp.ahead_behind {
+        (0, 0) => "even".to_string(),
+        (ahead, behind) => format_arrows(ahead, behind, emoji),
+    };
+    format!(
+        " [{}{}: {}]",
+        label,
+        cmp.name.if_supports_color(Stdout, |t| t.dimmed()),
+        position
+    )
+}
+
+/// `v N ^ M` (or emoji arrows), omitting zero sides
+fn format_arrows(ahead: usize, behind: usize, emoji: bool) -> String {
+    let mut parts = Vec::new();
+    if behind > 0 {
+        let arrow = if emoji { "⬇️" } else { "v" };
+        parts.push(format!(
+            "{} {}",
+            arrow,
+            behind.if_supports_color(Stdout, |t| t.red())
+        ));
+    }
+    if ahead > 0 {
+        let arrow = if emoji { "⬆️" } else { "^" };
+        parts.push(format!(
+            "{} {}",
+            arrow,
+            ahead.if_supports_color(Stdout, |t| t.green())
+        ));
+    }
+    parts.join(" ")
+}
+
+fn format_working_tree(tree: &WorkingTreeStatus, emoji: bool) -> String {
+    let mut parts = Vec::new();
+    if tree.conflicted > 0 {
+        parts.push(
+            format!("{} conflicted", tree.conflicted)
+                .if_supports_color(Stdout, |t| t.red())
+                .to_string(),
+        );
+    }
+    if tree.staged > 0 {
+        parts.push(
+            format!("{} staged", tree.staged)
+                .if_supports_color(Stdout, |t| t.green())
+                .to_string(),
+        );
+    }
+    if tree.unstaged > 0 {
+        parts.push(
+            format!("{} unstaged", tree.unstaged)
+                .if_supports_color(Stdout, |t| t.yellow())
+                .to_string(),
+        );
+    }
+    if tree.untracked > 0 {
+        parts.push(format!("{} untracked", tree.untracked));
+    }
+
+    let marker = if emoji { "✏️ " } else { "* " };
+    format!(" {}{}", marker, parts.join(", "))
+}
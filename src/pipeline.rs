@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::autopull;
+use crate::autopush;
+use crate::fetch;
+use crate::hooks::Hooks;
+use crate::journal::Journal;
+use crate::output;
+use crate::progress::Progress;
+use crate::report::{self, Event, Events};
+use crate::status;
+use crate::types::{
+    AppConfig, DiscoveredRepo, FetchOutcome, FetchResult, OutputFormat, RepoStatus, SkipReason,
+};
+
+/// Fetch, gather status, auto-pull and print each repo as it completes.
+///
+/// Fetches run under the network limits; as each one finishes its worktree
+/// status is gathered (bounded separately by `status_concurrency`, since
+/// those are local git queries) and it is auto-pulled. Blocks are printed
+/// in discovery order, each as soon as it and every repo before it are
+/// done, so output is deterministic while still streaming. Returns all
+/// statuses in that order.
+pub async fn run(
+    repos: Vec<DiscoveredRepo>,
+    config: &AppConfig,
+    cancel: &CancellationToken,
+) -> Vec<RepoStatus> {
+    let progress = Arc::new(Progress::new(repos.len(), config.quiet || !config.fetch));
+    let events = Events::new(config, progress.clone());
+    events.emit(|| {
+        let start = Event::Start {
+            schema_version: report::SCHEMA_VERSION,
+            repos: repos.len(),
+        };
+        std::iter::once(start).chain(repos.iter().map(report::discovered))
+    });
+    let order: HashMap<_, _> = repos
+        .iter()
+        .enumerate()
+        .map(|(index, repo)| (repo.path.clone(), index))
+        .collect();
+
+    // Stage 1: fetch results, in completion order
+    let (fetch_tx, mut fetch_rx) = mpsc::unbounded_channel();
+    let fetcher = if config.fetch {
+        let config = config.clone();
+        let cancel = cancel.clone();
+        let progress = progress.clone();
+        let events = events.clone();
+        Some(tokio::spawn(async move {
+            fetch::fetch_all_repos(repos, &config, cancel, progress, events, fetch_tx).await
+        }))
+    } else {
+        for repo in repos {
+            let _ = fetch_tx.send(FetchResult {
+                repo,
+                outcome: FetchOutcome::Skipped(SkipReason::Disabled),
+            });
+        }
+        // Close the channel so the status stage knows there is nothing more
+        drop(fetch_tx);
+        None
+    };
+
+    // Stage 2: worktree status and auto-pull for each fetched repo. Repos
+    // are pulled concurrently under `concurrency`; worktrees of one repo are
+    // pulled one after another since they share refs and lock files.
+    let (status_tx, mut status_rx) = mpsc::unbounded_channel();
+    let status_limit = Arc::new(Semaphore::new(config.status_concurrency.max(1)));
+    let updater = Arc::new(Updater {
+        config: config.clone(),
+        journal: Journal::open(),
+        hooks: Hooks::new(config),
+        limit: Semaphore::new(config.concurrency.max(1)),
+        cancel: cancel.clone(),
+    });
+    let keep_branches = config.all_branches || config.ff_branches;
+    let dispatcher = tokio::spawn(async move {
+        let mut tasks = Vec::new();
+        while let Some(result) = fetch_rx.recv().await {
+            events.emit(|| [report::fetch_finished(&result)]);
+            let index = order[&result.repo.path];
+            let path = result.repo.path.clone();
+            let status_limit = status_limit.clone();
+            let updater = updater.clone();
+            let events = events.clone();
+            let status_tx = status_tx.clone();
+            let task = tokio::spawn(async move {
+                let mut status =
+                    status::build_repo_status(result, status_limit, keep_branches).await;
+                events.emit(|| report::status_event(&status, &updater.config));
+                updater.update(&mut status).await;
+                events.emit(|| report::update_events(&status));
+                let _ = status_tx.send((index, status));
+            });
+            tasks.push((path, task));
+        }
+        for (path, task) in tasks {
+            if let Err(e) = task.await {
+                eprintln!("Warning: repo task panicked for {}: {}", path.display(), e);
+            }
+        }
+    });
+
+    // Stage 3: print, one repo at a time in discovery order (JSON is
+    // printed as one document once everything is done, NDJSON events were
+    // already written as they happened)
+    let mut statuses = Vec::new();
+    let mut pending = BTreeMap::new();
+    while let Some((index, status)) = status_rx.recv().await {
+        pending.insert(index, status);
+        while let Some(status) = pending.remove(&statuses.len()) {
+            if config.format == OutputFormat::Text {
+                progress.suspend(|| output::print_repo_status(&status, config));
+            }
+            statuses.push(status);
+        }
+    }
+    // A repo whose task failed leaves a gap; print the ones held back behind it
+    for status in pending.into_values() {
+        if config.format == OutputFormat::Text {
+            progress.suspend(|| output::print_repo_status(&status, config));
+        }
+        statuses.push(status);
+    }
+
+    if let Some(fetcher) = fetcher {
+        let _ = fetcher.await;
+    }
+    let _ = dispatcher.await;
+
+    statuses
+}
+
+/// Auto-pull, branch fast-forwarding and auto-push, shared by all repo tasks
+struct Updater {
+    config: AppConfig,
+    journal: Journal,
+    hooks: Hooks,
+    /// Bounds how many repos are being updated at once
+    limit: Semaphore,
+    cancel: CancellationToken,
+}
+
+impl Updater {
+    async fn update(&self, status: &mut RepoStatus) {
+        let config = &self.config;
+        if config.auto_pull || config.ff_branches || config.auto_push {
+            let _permit = self.limit.acquire().await.unwrap();
+            // Don't start modifying worktrees after Ctrl-C
+            if config.auto_pull && !self.cancel.is_cancelled() {
+                autopull::auto_pull_eligible(status, config, &self.journal, &self.hooks).await;
+            }
+            if config.ff_branches && !self.cancel.is_cancelled() {
+                autopull::fast_forward_branches(status, config, &self.journal).await;
+            }
+            if config.auto_push && !self.cancel.is_cancelled() {
+                autopush::auto_push_eligible(status, config).await;
+            }
+            status.sync_branches();
+        }
+        // Branches were only kept to fast-forward them; list just those
+        if !config.all_branches {
+            status.branches.retain(|b| b.pull_result.is_some());
+        }
+    }
+}
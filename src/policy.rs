@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+use crate::types::{AppConfig, PolicyRule};
+
+/// Whether auto-pull rules permit touching `branch` in the repo at `repo_path`.
+///
+/// A deny match always wins. Otherwise, non-empty allow lists must match:
+/// the repo by path glob or group, and the branch by name pattern.
+pub fn pull_allowed(config: &AppConfig, repo_path: &Path, branch: &str) -> bool {
+    let policy = &config.auto_pull_policy;
+    let repo_path = canonical_path(repo_path);
+    let repo = repo_path.to_string_lossy();
+
+    let repo_matches = |rule: &PolicyRule| {
+        rule.repos.iter().any(|glob| glob_match(glob, &repo))
+            || rule.groups.iter().any(|name| {
+                config
+                    .groups
+                    .get(name)
+                    .is_some_and(|globs| globs.iter().any(|glob| glob_match(glob, &repo)))
+            })
+    };
+    let branch_matches = |rule: &PolicyRule| {
+        rule.branches
+            .iter()
+            .any(|pattern| glob_match(pattern, branch))
+    };
+
+    let deny = &policy.deny;
+    if repo_matches(deny) || branch_matches(deny) {
+        return false;
+    }
+
+    let allow = &policy.allow;
+    let repo_allowed = (allow.repos.is_empty() && allow.groups.is_empty()) || repo_matches(allow);
+    let branch_allowed = allow.branches.is_empty() || branch_matches(allow);
+    repo_allowed && branch_allowed
+}
+
+/// The path repo globs are matched against: canonical, so `..` and
+/// symlinks can't dodge a rule, or just absolute if it doesn't exist
+pub fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Match `text` against a glob: `*` and `?` stay within one path
+/// component, `**` crosses them (`**/` also matches no directory at all).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            glob_match_chars(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && glob_match_chars(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match_chars(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match_chars(rest, &text[i..])),
+        ['?', rest @ ..] => {
+            text.first().is_some_and(|&c| c != '/') && glob_match_chars(rest, &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_match_chars(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use super::*;
+    use crate::types::PullPolicy;
+
+    #[test]
+    fn glob_star_stays_within_a_component() {
+        assert!(glob_match("feature/*", "feature/login"));
+        assert!(!glob_match("feature/*", "feature/login/form"));
+        assert!(!glob_match("feature/*", "feature"));
+        assert!(glob_match("*", "main"));
+        assert!(!glob_match("*", "release/1.0"));
+        assert!(glob_match("/src/*-api", "/src/billing-api"));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_char_but_not_a_slash() {
+        assert!(glob_match("v?", "v1"));
+        assert!(!glob_match("v?", "v10"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn glob_double_star_crosses_components() {
+        assert!(glob_match("/src/**", "/src/a/b/c"));
+        assert!(glob_match("/src/**/api", "/src/api"));
+        assert!(glob_match("/src/**/api", "/src/team/x/api"));
+        assert!(!glob_match("/src/**/api", "/src/team/x/api-v2"));
+        assert!(!glob_match("/src/**/api", "/srcx/api"));
+    }
+
+    #[test]
+    fn glob_literal_must_match_exactly() {
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "mainline"));
+        assert!(!glob_match("main", "mai"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "x"));
+    }
+
+    fn rule(repos: &[&str], branches: &[&str], groups: &[&str]) -> PolicyRule {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        PolicyRule {
+            repos: strings(repos),
+            branches: strings(branches),
+            groups: strings(groups),
+        }
+    }
+
+    fn config(allow: PolicyRule, deny: PolicyRule) -> AppConfig {
+        AppConfig {
+            auto_pull_policy: PullPolicy { allow, deny },
+            ..AppConfig::default()
+        }
+    }
+
+    /// A workspace with repo directories `a` and `b`, returned canonical
+    fn workspace() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("a")).unwrap();
+        fs::create_dir(root.join("b")).unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn no_rules_allow_everything() {
+        let (_dir, root) = workspace();
+        let config = config(PolicyRule::default(), PolicyRule::default());
+        assert!(pull_allowed(&config, &root.join("a"), "main"));
+    }
+
+    #[test]
+    fn deny_by_repo_or_branch() {
+        let (_dir, root) = workspace();
+        let config = config(
+            PolicyRule::default(),
+            rule(&[&format!("{}/a", root.display())], &["release/*"], &[]),
+        );
+        assert!(!pull_allowed(&config, &root.join("a"), "main"));
+        assert!(!pull_allowed(&config, &root.join("b"), "release/1.0"));
+        assert!(pull_allowed(&config, &root.join("b"), "main"));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let (_dir, root) = workspace();
+        let config = config(
+            rule(&[&format!("{}/*", root.display())], &[], &[]),
+            rule(&[], &["main"], &[]),
+        );
+        assert!(!pull_allowed(&config, &root.join("a"), "main"));
+        assert!(pull_allowed(&config, &root.join("a"), "develop"));
+    }
+
+    #[test]
+    fn allow_lists_must_match() {
+        let (_dir, root) = workspace();
+        let config = config(
+            rule(&[&format!("{}/a", root.display())], &["main"], &[]),
+            PolicyRule::default(),
+        );
+        assert!(pull_allowed(&config, &root.join("a"), "main"));
+        assert!(!pull_allowed(&config, &root.join("a"), "develop"));
+        assert!(!pull_allowed(&config, &root.join("b"), "main"));
+    }
+
+    #[test]
+    fn groups_match_their_globs() {
+        let (_dir, root) = workspace();
+        let mut config = config(rule(&[], &[], &["work", "missing"]), PolicyRule::default());
+        config.groups =
+            HashMap::from([("work".to_string(), vec![format!("{}/b", root.display())])]);
+        assert!(pull_allowed(&config, &root.join("b"), "main"));
+        assert!(!pull_allowed(&config, &root.join("a"), "main"));
+    }
+
+    #[test]
+    fn dot_dot_paths_cannot_dodge_deny() {
+        let (_dir, root) = workspace();
+        let config = config(
+            PolicyRule::default(),
+            rule(&[&format!("{}/*", root.display())], &[], &[]),
+        );
+        let sneaky = root.join("a").join("..").join("b");
+        assert!(!pull_allowed(&config, &sneaky, "main"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_dodge_deny() {
+        let (_dir, root) = workspace();
+        let link = tempfile::tempdir().unwrap();
+        let linked = link.path().join("a");
+        std::os::unix::fs::symlink(root.join("a"), &linked).unwrap();
+        let config = config(
+            PolicyRule::default(),
+            rule(&[&format!("{}/a", root.display())], &[], &[]),
+        );
+        assert!(!pull_allowed(&config, &linked, "main"));
+    }
+}
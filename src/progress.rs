@@ -0,0 +1,132 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Fetch progress reporting on stderr.
+///
+/// On a TTY this is a live view with one line per in-flight repo and a
+/// completion bar; otherwise it prints one plain line per finished repo.
+pub struct Progress {
+    mode: Mode,
+    total: usize,
+    done: AtomicUsize,
+}
+
+enum Mode {
+    Live {
+        multi: MultiProgress,
+        bar: ProgressBar,
+    },
+    Plain,
+    Hidden,
+}
+
+/// A repo in flight; its live line is cleared when this is dropped.
+pub struct RepoProgress<'a> {
+    progress: &'a Progress,
+    name: String,
+    line: Option<ProgressBar>,
+}
+
+impl Progress {
+    pub fn new(total: usize, quiet: bool) -> Self {
+        let mode = if quiet {
+            Mode::Hidden
+        } else if std::io::stderr().is_terminal() {
+            let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stderr());
+            let bar = multi.add(ProgressBar::new(total as u64));
+            bar.set_style(
+                ProgressStyle::with_template("Fetching [{bar:30}] {pos}/{len}")
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            Mode::Live { multi, bar }
+        } else {
+            Mode::Plain
+        };
+
+        Self {
+            mode,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Show `name` as in flight until the returned handle is dropped.
+    pub fn start(&self, name: String) -> RepoProgress<'_> {
+        let line = match &self.mode {
+            Mode::Live { multi, bar } => {
+                let line = multi.insert_before(bar, ProgressBar::new_spinner());
+                line.set_style(
+                    ProgressStyle::with_template("  {spinner} {msg} ({elapsed})").unwrap(),
+                );
+                line.set_message(name.clone());
+                line.enable_steady_tick(Duration::from_millis(100));
+                Some(line)
+            }
+            Mode::Plain | Mode::Hidden => None,
+        };
+
+        RepoProgress {
+            progress: self,
+            name,
+            line,
+        }
+    }
+
+    /// Count a repo that finished without ever being in flight.
+    pub fn skip(&self, name: &str) {
+        self.advance(name);
+    }
+
+    /// Print a message without garbling the live view.
+    pub fn println(&self, msg: &str) {
+        match &self.mode {
+            Mode::Live { multi, .. } => {
+                let _ = multi.println(msg);
+            }
+            Mode::Plain | Mode::Hidden => eprintln!("{}", msg),
+        }
+    }
+
+    /// Hide the live view while `f` writes to the terminal.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.mode {
+            Mode::Live { multi, .. } => multi.suspend(f),
+            Mode::Plain | Mode::Hidden => f(),
+        }
+    }
+
+    /// Remove the live view once all repos are done.
+    pub fn finish(&self) {
+        if let Mode::Live { multi, bar } = &self.mode {
+            bar.finish_and_clear();
+            let _ = multi.clear();
+        }
+    }
+
+    fn advance(&self, name: &str) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        match &self.mode {
+            Mode::Live { bar, .. } => bar.inc(1),
+            Mode::Plain => eprintln!("[{}/{}] {}", done, self.total, name),
+            Mode::Hidden => {}
+        }
+    }
+}
+
+impl RepoProgress<'_> {
+    pub fn finish(self) {
+        self.progress.advance(&self.name);
+    }
+}
+
+impl Drop for RepoProgress<'_> {
+    fn drop(&mut self) {
+        if let Some(line) = &self.line {
+            line.finish_and_clear();
+        }
+    }
+}
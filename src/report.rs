@@ -0,0 +1,539 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::output;
+use crate::policy::canonical_path;
+use crate::progress::Progress;
+use crate::types::{
+    AppConfig, DiscoveredRepo, FetchOutcome, FetchResult, HookOutcome, LocalBranch, OutputFormat,
+    PullResult, PushResult, RefComparison, RepoKind, RepoStatus, SkipReason, StashEntry,
+    WorktreeInfo,
+};
+
+/// Version of the JSON schema below. Bumped only for incompatible changes
+/// (removed or renamed fields, changed meaning); new fields may be added
+/// without a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Machine-readable results of a run, as printed by `--format json`.
+/// `--format ndjson` streams the same parts as [`Event`]s.
+///
+/// These types are the stable schema; they mirror the internal status
+/// types but are kept separate so those can change freely.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub repos: Vec<RepoReport>,
+    pub summary: Summary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoReport {
+    pub name: String,
+    pub path: String,
+    pub bare: bool,
+    pub fetch: FetchReport,
+    pub worktrees: Vec<WorktreeReport>,
+    /// Local branches; only listed with `--all-branches`, or when
+    /// `--ff-branches` moved them
+    pub branches: Vec<BranchReport>,
+    pub stashes: Vec<StashReport>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum FetchReport {
+    Updated {
+        refs_updated: usize,
+    },
+    NoChanges,
+    NoRemote,
+    /// `reason` is `disabled` or `fetched_recently`
+    Skipped {
+        reason: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fetched_secs_ago: Option<u64>,
+    },
+    Cancelled,
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorktreeReport {
+    pub path: String,
+    pub branch: Option<String>,
+    /// Short commit id when HEAD is detached
+    pub detached_head: Option<String>,
+    pub upstream: Option<UpstreamReport>,
+    /// Against the upstream
+    pub ahead_behind: Option<AheadBehind>,
+    /// Against the remote's default branch, when that isn't the upstream
+    pub vs_default: Option<ComparisonReport>,
+    /// Against `@{push}`, when that isn't the upstream
+    pub vs_push: Option<ComparisonReport>,
+    pub working_tree: Option<WorkingTreeReport>,
+    /// `rebase`, `am`, `merge`, `cherry-pick`, `revert` or `bisect`
+    pub operation: Option<&'static str>,
+    pub stale_submodules: Vec<String>,
+    pub push: Option<PushTargetReport>,
+    pub pull_result: Option<PullReport>,
+    pub push_result: Option<PushReport>,
+    pub hooks: Vec<HookReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamReport {
+    pub name: String,
+    pub gone: bool,
+    pub oid: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AheadBehind {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComparisonReport {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkingTreeReport {
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushTargetReport {
+    pub remote: String,
+    pub remote_ref: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum PullReport {
+    Pulled,
+    Rebased,
+    RebaseAborted,
+    NotAllowed,
+    Skipped { reason: String },
+    Failed { error: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum PushReport {
+    Pushed,
+    WouldPush,
+    Failed { error: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct HookReport {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub output: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchReport {
+    pub name: String,
+    /// Full ref, e.g. `refs/remotes/origin/main`
+    pub upstream: Option<String>,
+    pub upstream_gone: bool,
+    pub ahead_behind: Option<AheadBehind>,
+    pub worktree: Option<String>,
+    pub pull_result: Option<PullReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StashReport {
+    pub index: usize,
+    pub branch: Option<String>,
+    pub message: String,
+    /// Unix time
+    pub created: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub repos: usize,
+    pub with_changes: usize,
+    pub errors: usize,
+    pub cancelled: usize,
+    /// Worktrees and branches pulled, rebased or fast-forwarded
+    pub pulled: usize,
+    /// Pulls that were attempted and failed or aborted
+    pub pull_failures: usize,
+    pub pushed: usize,
+    pub push_failures: usize,
+    pub interrupted: bool,
+}
+
+/// One line of `--format ndjson` output, written as soon as it happens
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Always the first event
+    Start {
+        schema_version: u32,
+        repos: usize,
+    },
+    Discovered {
+        name: String,
+        path: String,
+    },
+    FetchStarted {
+        name: String,
+        path: String,
+    },
+    /// Sent for every repo, including those that were never fetched
+    FetchFinished {
+        name: String,
+        path: String,
+        fetch: FetchReport,
+    },
+    /// Repo state before auto-pull and auto-push
+    Status(RepoReport),
+    /// A worktree or, with `worktree: null`, a branch auto-pull acted on
+    Pull {
+        name: String,
+        path: String,
+        worktree: Option<String>,
+        branch: String,
+        result: PullReport,
+        /// Against the upstream, after the pull
+        ahead_behind: Option<AheadBehind>,
+    },
+    Push {
+        name: String,
+        path: String,
+        worktree: String,
+        branch: String,
+        result: PushReport,
+    },
+    /// Always the last event
+    Summary(Summary),
+}
+
+/// Print every repo and the summary as one JSON document.
+///
+/// Unlike text output, repos without changes are included; `--filter` and
+/// `--stashes-older-than` still apply.
+pub fn print_json(statuses: &[RepoStatus], config: &AppConfig, interrupted: bool) -> Result<()> {
+    let repos = statuses
+        .iter()
+        .filter_map(|status| {
+            let worktrees = output::selected_worktrees(status, config)?;
+            Some(repo_report(status, &worktrees))
+        })
+        .collect();
+    let report = Report {
+        schema_version: SCHEMA_VERSION,
+        repos,
+        summary: summary(statuses, interrupted),
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Writes `--format ndjson` events to stdout; does nothing for other formats
+#[derive(Clone)]
+pub struct Events {
+    enabled: bool,
+    progress: Arc<Progress>,
+}
+
+impl Events {
+    pub fn new(config: &AppConfig, progress: Arc<Progress>) -> Self {
+        Self {
+            enabled: config.format == OutputFormat::Ndjson,
+            progress,
+        }
+    }
+
+    /// Write the events built by `events`, which is only called when enabled
+    pub fn emit<I: IntoIterator<Item = Event>>(&self, events: impl FnOnce() -> I) {
+        if !self.enabled {
+            return;
+        }
+        for event in events() {
+            self.progress.suspend(|| print_event(&event));
+        }
+    }
+}
+
+/// Print one event as a line of JSON
+pub fn print_event(event: &Event) {
+    match serde_json::to_string(event) {
+        Ok(line) => println!("{}", line),
+        Err(e) => eprintln!("Warning: failed to serialize event: {}", e),
+    }
+}
+
+pub fn discovered(repo: &DiscoveredRepo) -> Event {
+    Event::Discovered {
+        name: repo.name(),
+        path: path_string(&repo.path),
+    }
+}
+
+pub fn fetch_started(repo: &DiscoveredRepo) -> Event {
+    Event::FetchStarted {
+        name: repo.name(),
+        path: path_string(&repo.path),
+    }
+}
+
+pub fn fetch_finished(result: &FetchResult) -> Event {
+    Event::FetchFinished {
+        name: result.repo.name(),
+        path: path_string(&result.repo.path),
+        fetch: fetch_report(&result.outcome),
+    }
+}
+
+/// The repo's status, unless `--filter` or `--stashes-older-than` exclude it
+pub fn status_event(status: &RepoStatus, config: &AppConfig) -> Option<Event> {
+    let worktrees = output::selected_worktrees(status, config)?;
+    Some(Event::Status(repo_report(status, &worktrees)))
+}
+
+/// A `pull` event per worktree or branch auto-pull acted on, then a `push`
+/// event per worktree auto-push acted on
+pub fn update_events(status: &RepoStatus) -> Vec<Event> {
+    let name = status.repo.name();
+    let path = path_string(&status.repo.path);
+    let mut events = Vec::new();
+
+    for wt in &status.worktrees {
+        if let (Some(branch), Some(result)) = (&wt.branch, &wt.pull_result) {
+            events.push(Event::Pull {
+                name: name.clone(),
+                path: path.clone(),
+                worktree: Some(path_string(&wt.path)),
+                branch: branch.clone(),
+                result: pull_report(result),
+                ahead_behind: wt.ahead_behind.map(ahead_behind),
+            });
+        }
+    }
+    for branch in &status.branches {
+        if let Some(result) = &branch.pull_result {
+            events.push(Event::Pull {
+                name: name.clone(),
+                path: path.clone(),
+                worktree: None,
+                branch: branch.name.clone(),
+                result: pull_report(result),
+                ahead_behind: branch.ahead_behind.map(ahead_behind),
+            });
+        }
+    }
+    for wt in &status.worktrees {
+        if let (Some(branch), Some(result)) = (&wt.branch, &wt.push_result) {
+            events.push(Event::Push {
+                name: name.clone(),
+                path: path.clone(),
+                worktree: path_string(&wt.path),
+                branch: branch.clone(),
+                result: push_report(result),
+            });
+        }
+    }
+
+    events
+}
+
+fn repo_report(status: &RepoStatus, worktrees: &[&WorktreeInfo]) -> RepoReport {
+    RepoReport {
+        name: status.repo.name(),
+        path: path_string(&status.repo.path),
+        bare: status.repo.kind == RepoKind::Bare,
+        fetch: fetch_report(&status.fetch_outcome),
+        worktrees: worktrees.iter().map(|wt| worktree_report(wt)).collect(),
+        branches: status.branches.iter().map(branch_report).collect(),
+        stashes: status.stashes.iter().map(stash_report).collect(),
+    }
+}
+
+pub fn summary(statuses: &[RepoStatus], interrupted: bool) -> Summary {
+    let mut summary = Summary {
+        repos: statuses.len(),
+        with_changes: 0,
+        errors: 0,
+        cancelled: 0,
+        pulled: 0,
+        pull_failures: 0,
+        pushed: 0,
+        push_failures: 0,
+        interrupted,
+    };
+
+    for status in statuses {
+        if output::has_changes(status) {
+            summary.with_changes += 1;
+        }
+        match status.fetch_outcome {
+            FetchOutcome::Error(_) => summary.errors += 1,
+            FetchOutcome::Cancelled => summary.cancelled += 1,
+            _ => {}
+        }
+
+        let worktree_pulls = status.worktrees.iter().map(|wt| &wt.pull_result);
+        let branch_pulls = status.branches.iter().map(|b| &b.pull_result);
+        for pull in worktree_pulls.chain(branch_pulls).flatten() {
+            match pull {
+                PullResult::Pulled | PullResult::Rebased => summary.pulled += 1,
+                PullResult::RebaseAborted | PullResult::Failed(_) => summary.pull_failures += 1,
+                PullResult::NotAllowed | PullResult::Skipped(_) => {}
+            }
+        }
+        for wt in &status.worktrees {
+            match wt.push_result {
+                Some(PushResult::Pushed) => summary.pushed += 1,
+                Some(PushResult::PushFailed(_)) => summary.push_failures += 1,
+                Some(PushResult::WouldPush) | None => {}
+            }
+        }
+    }
+
+    summary
+}
+
+fn fetch_report(outcome: &FetchOutcome) -> FetchReport {
+    match outcome {
+        FetchOutcome::Updated { refs_updated } => FetchReport::Updated {
+            refs_updated: *refs_updated,
+        },
+        FetchOutcome::NoChanges => FetchReport::NoChanges,
+        FetchOutcome::NoRemote => FetchReport::NoRemote,
+        FetchOutcome::Skipped(SkipReason::Disabled) => FetchReport::Skipped {
+            reason: "disabled",
+            fetched_secs_ago: None,
+        },
+        FetchOutcome::Skipped(SkipReason::FetchedRecently(age)) => FetchReport::Skipped {
+            reason: "fetched_recently",
+            fetched_secs_ago: Some(age.as_secs()),
+        },
+        FetchOutcome::Cancelled => FetchReport::Cancelled,
+        FetchOutcome::Error(e) => FetchReport::Error { message: e.clone() },
+    }
+}
+
+fn worktree_report(wt: &WorktreeInfo) -> WorktreeReport {
+    WorktreeReport {
+        path: path_string(&wt.path),
+        branch: wt.branch.clone(),
+        detached_head: wt.detached_head.clone(),
+        upstream: wt.upstream.as_ref().map(|u| UpstreamReport {
+            name: u.name.clone(),
+            gone: u.gone,
+            oid: u.oid.clone(),
+        }),
+        ahead_behind: wt.ahead_behind.map(ahead_behind),
+        vs_default: wt.vs_default.as_ref().map(comparison_report),
+        vs_push: wt.vs_push.as_ref().map(comparison_report),
+        working_tree: wt.working_tree.map(|tree| WorkingTreeReport {
+            staged: tree.staged,
+            unstaged: tree.unstaged,
+            untracked: tree.untracked,
+            conflicted: tree.conflicted,
+        }),
+        operation: wt.operation.map(|op| op.label()),
+        stale_submodules: wt.stale_submodules.clone(),
+        push: wt.push.as_ref().map(|target| PushTargetReport {
+            remote: target.remote.clone(),
+            remote_ref: target.remote_ref.clone(),
+        }),
+        pull_result: wt.pull_result.as_ref().map(pull_report),
+        push_result: wt.push_result.as_ref().map(push_report),
+        hooks: wt.hooks.iter().map(hook_report).collect(),
+    }
+}
+
+fn branch_report(branch: &LocalBranch) -> BranchReport {
+    BranchReport {
+        name: branch.name.clone(),
+        upstream: branch.upstream.clone(),
+        upstream_gone: branch.upstream_gone,
+        ahead_behind: branch.ahead_behind.map(ahead_behind),
+        worktree: branch.worktree.as_deref().map(path_string),
+        pull_result: branch.pull_result.as_ref().map(pull_report),
+    }
+}
+
+fn stash_report(stash: &StashEntry) -> StashReport {
+    StashReport {
+        index: stash.index,
+        branch: stash.branch.clone(),
+        message: stash.message.clone(),
+        created: unix_time(stash.created),
+    }
+}
+
+fn pull_report(result: &PullResult) -> PullReport {
+    match result {
+        PullResult::Pulled => PullReport::Pulled,
+        PullResult::Rebased => PullReport::Rebased,
+        PullResult::RebaseAborted => PullReport::RebaseAborted,
+        PullResult::NotAllowed => PullReport::NotAllowed,
+        PullResult::Skipped(reason) => PullReport::Skipped {
+            reason: reason.clone(),
+        },
+        PullResult::Failed(e) => PullReport::Failed { error: e.clone() },
+    }
+}
+
+fn push_report(result: &PushResult) -> PushReport {
+    match result {
+        PushResult::Pushed => PushReport::Pushed,
+        PushResult::WouldPush => PushReport::WouldPush,
+        PushResult::PushFailed(e) => PushReport::Failed { error: e.clone() },
+    }
+}
+
+fn hook_report(hook: &HookOutcome) -> HookReport {
+    HookReport {
+        command: hook.command.clone(),
+        success: hook.success,
+        exit_code: hook.exit_code,
+        output: hook.output.clone(),
+    }
+}
+
+fn comparison_report(cmp: &RefComparison) -> ComparisonReport {
+    ComparisonReport {
+        name: cmp.name.clone(),
+        ahead: cmp.ahead_behind.0,
+        behind: cmp.ahead_behind.1,
+    }
+}
+
+fn ahead_behind((ahead, behind): (usize, usize)) -> AheadBehind {
+    AheadBehind { ahead, behind }
+}
+
+/// Paths are reported absolute, whatever `-d` was given as
+fn path_string(path: &Path) -> String {
+    canonical_path(path).to_string_lossy().to_string()
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,28 +1,70 @@
-use crate::git;
-use crate::types::{FetchResult, RepoKind, RepoStatus, WorktreeInfo};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
 
-pub async fn build_repo_statuses(fetch_results: Vec<FetchResult>) -> Vec<RepoStatus> {
-    let mut statuses = Vec::with_capacity(fetch_results.len());
+use crate::git;
+use crate::types::{
+    FetchOutcome, FetchResult, LocalBranch, PushTarget, RefComparison, RepoKind, RepoStatus,
+    Upstream, WorktreeInfo,
+};
 
-    for result in fetch_results {
-        let worktrees = gather_worktrees(&result).await;
-        statuses.push(RepoStatus {
+/// Gather worktree status for one repo.
+///
+/// `limit` bounds how many worktrees (across all repos) are inspected at
+/// once; worktrees of a repo are inspected concurrently but keep the order
+/// `git worktree list` reports them in. With `keep_branches`, every local
+/// branch is kept in the status too.
+pub async fn build_repo_status(
+    result: FetchResult,
+    limit: Arc<Semaphore>,
+    keep_branches: bool,
+) -> RepoStatus {
+    // Interrupted repos are reported as-is without further git queries
+    if matches!(result.outcome, FetchOutcome::Cancelled) {
+        return RepoStatus {
             repo: result.repo,
             fetch_outcome: result.outcome,
-            worktrees,
-        });
+            worktrees: Vec::new(),
+            stashes: Vec::new(),
+            branches: Vec::new(),
+        };
     }
 
-    statuses
+    let stashes = {
+        let _permit = limit.acquire().await.unwrap();
+        git::list_stashes(&result.repo.path, result.repo.kind).await
+    };
+    let (worktrees, branches) = gather_worktrees(&result, limit).await;
+
+    RepoStatus {
+        repo: result.repo,
+        fetch_outcome: result.outcome,
+        worktrees,
+        stashes,
+        branches: if keep_branches { branches } else { Vec::new() },
+    }
 }
 
-async fn gather_worktrees(result: &FetchResult) -> Vec<WorktreeInfo> {
+async fn gather_worktrees(
+    result: &FetchResult,
+    limit: Arc<Semaphore>,
+) -> (Vec<WorktreeInfo>, Vec<LocalBranch>) {
     let repo = &result.repo;
 
+    let (listed, branches, defaults) = {
+        let _permit = limit.acquire().await.unwrap();
+        (
+            git::list_worktrees(&repo.path, repo.kind).await,
+            git::local_branches(&repo.path, repo.kind).await,
+            git::remote_default_branches(&repo.path, repo.kind).await,
+        )
+    };
+
     // For bare repos, list worktrees via git worktree list
     // For non-bare repos, the repo itself is the worktree
-    let mut worktrees = match repo.kind {
-        RepoKind::Bare => match git::list_worktrees(&repo.path, repo.kind).await {
+    let worktrees: Vec<WorktreeInfo> = match repo.kind {
+        RepoKind::Bare => match listed {
             Ok(wts) => wts
                 .into_iter()
                 .filter(|wt| {
@@ -34,19 +76,154 @@ async fn gather_worktrees(result: &FetchResult) -> Vec<WorktreeInfo> {
         },
         RepoKind::NonBare => {
             // For non-bare, get the branch name
-            match git::list_worktrees(&repo.path, repo.kind).await {
-                Ok(wts) => wts,
-                Err(_) => Vec::new(),
-            }
+            listed.unwrap_or_default()
         }
     };
 
-    // Gather ahead/behind for each worktree
-    for wt in &mut worktrees {
-        if wt.branch.is_some() && wt.detached_head.is_none() {
-            wt.ahead_behind = git::ahead_behind(&wt.path).await;
+    // Inspect worktrees concurrently; awaiting the handles in order keeps
+    // the output order independent of which finishes first
+    let handles: Vec<_> = worktrees
+        .into_iter()
+        .map(|mut wt| {
+            let limit = limit.clone();
+            wt.upstream = upstream_for(&wt, &branches);
+            wt.push = push_for(&wt, &branches);
+            let default_ref = default_ref_for(&wt, &branches, &defaults);
+            // Pushing to a fork, say, rather than the upstream
+            let push_ref = wt
+                .push
+                .as_ref()
+                .filter(|target| !target.is_upstream)
+                .map(|target| target.tracking.clone());
+            tokio::spawn(async move {
+                let _permit = limit.acquire().await.unwrap();
+                inspect_worktree(wt, default_ref, push_ref).await
+            })
+        })
+        .collect();
+
+    let mut gathered = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(wt) => gathered.push(wt),
+            Err(e) => eprintln!("Warning: status task panicked: {}", e),
         }
     }
+    (gathered, branches)
+}
+
+fn upstream_for(wt: &WorktreeInfo, branches: &[LocalBranch]) -> Option<Upstream> {
+    if wt.detached_head.is_some() {
+        return None;
+    }
+    let name = wt.branch.as_ref()?;
+    let branch = branches.iter().find(|b| &b.name == name)?;
+    let upstream = branch.upstream.as_ref()?;
+    Some(Upstream {
+        name: upstream
+            .strip_prefix("refs/remotes/")
+            .unwrap_or(upstream)
+            .to_string(),
+        gone: branch.upstream_gone,
+        oid: None,
+    })
+}
+
+fn push_for(wt: &WorktreeInfo, branches: &[LocalBranch]) -> Option<PushTarget> {
+    if wt.detached_head.is_some() {
+        return None;
+    }
+    let name = wt.branch.as_ref()?;
+    let branch = branches.iter().find(|b| &b.name == name)?;
+    let remote = branch.push_remote.clone()?;
+    // Without a configured mapping, push to the same name
+    let remote_ref = branch
+        .push_ref
+        .clone()
+        .unwrap_or_else(|| format!("refs/heads/{}", name));
+    // git can't always resolve `@{push}`, e.g. with `push.default=simple`
+    // when pushing to another remote than the upstream's (a fork); assume
+    // the standard fetch refspec for the push remote then
+    let tracking = branch.push_tracking.clone().unwrap_or_else(|| {
+        let name = remote_ref
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&remote_ref);
+        format!("refs/remotes/{}/{}", remote, name)
+    });
+    let is_upstream = branch.upstream.as_ref() == Some(&tracking);
+    Some(PushTarget {
+        remote,
+        remote_ref,
+        tracking,
+        is_upstream,
+    })
+}
+
+/// The remote default branch (full ref) to compare a worktree against.
+///
+/// Uses the remote of the branch's upstream, else `origin`, else the only
+/// remote. `None` when the upstream already is the default branch, since
+/// the regular ahead/behind covers that.
+fn default_ref_for(
+    wt: &WorktreeInfo,
+    branches: &[LocalBranch],
+    defaults: &HashMap<String, String>,
+) -> Option<String> {
+    let branch = wt
+        .branch
+        .as_ref()
+        .and_then(|name| branches.iter().find(|b| &b.name == name));
+
+    let remote = match branch.and_then(|b| b.remote.as_deref()) {
+        Some(remote) => remote,
+        None if defaults.contains_key("origin") => "origin",
+        None if defaults.len() == 1 => defaults.keys().next()?,
+        None => return None,
+    };
+    let default_ref = defaults.get(remote)?;
+
+    if branch.and_then(|b| b.upstream.as_ref()) == Some(default_ref) {
+        return None;
+    }
+    Some(default_ref.clone())
+}
+
+/// Ahead/behind of the worktree's HEAD against `full_ref`; `None` if that
+/// doesn't exist
+pub async fn compare_with(wt: &WorktreeInfo, full_ref: &str) -> Option<RefComparison> {
+    let ahead_behind = git::ahead_behind_of(&wt.path, full_ref).await?;
+    Some(RefComparison {
+        name: full_ref
+            .strip_prefix("refs/remotes/")
+            .unwrap_or(full_ref)
+            .to_string(),
+        ahead_behind,
+    })
+}
 
-    worktrees
+async fn inspect_worktree(
+    mut wt: WorktreeInfo,
+    default_ref: Option<String>,
+    push_ref: Option<String>,
+) -> WorktreeInfo {
+    // Pin the upstream commit so auto-pull moves to exactly what is shown
+    if let Some(upstream) = wt.upstream.as_mut().filter(|u| !u.gone) {
+        upstream.oid = git::rev_parse(&wt.path, RepoKind::NonBare, "HEAD@{upstream}")
+            .await
+            .ok();
+        if let Some(oid) = &upstream.oid {
+            wt.ahead_behind = git::ahead_behind_of(&wt.path, oid).await;
+        }
+    }
+    if let Some(default_ref) = default_ref {
+        wt.vs_default = compare_with(&wt, &default_ref).await;
+    }
+    // A branch that was never pushed has no @{push} ref yet
+    if let Some(push_ref) = push_ref {
+        wt.vs_push = compare_with(&wt, &push_ref).await;
+    }
+    wt.working_tree = git::working_tree_status(&wt.path).await;
+    wt.operation = git::operation_in_progress(&wt.path).await;
+    wt.stale_submodules = git::stale_submodules(&wt.path).await;
+    wt
 }
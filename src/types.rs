@@ -1,4 +1,8 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
 
 #[derive(Debug, Clone)]
 pub struct DiscoveredRepo {
@@ -6,6 +10,16 @@ pub struct DiscoveredRepo {
     pub kind: RepoKind,
 }
 
+impl DiscoveredRepo {
+    /// Display name: the directory name, or the full path if there is none
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RepoKind {
     Bare,
@@ -23,41 +37,369 @@ pub enum FetchOutcome {
     Updated { refs_updated: usize },
     NoChanges,
     NoRemote,
-    Skipped,
+    Skipped(SkipReason),
+    /// Not fetched (or fetch aborted) because the run was interrupted
+    Cancelled,
     Error(String),
 }
 
+#[derive(Debug, Clone)]
+pub enum SkipReason {
+    /// Fetching disabled via `--no-fetch` or config
+    Disabled,
+    /// Last fetched successfully less than `min_fetch_interval` ago
+    FetchedRecently(Duration),
+}
+
 #[derive(Debug, Clone)]
 pub struct WorktreeInfo {
     pub path: PathBuf,
     pub branch: Option<String>,
     pub detached_head: Option<String>,
     pub ahead_behind: Option<(usize, usize)>,
+    /// Configured upstream; `None` if there is none (or HEAD is detached)
+    pub upstream: Option<Upstream>,
+    pub working_tree: Option<WorkingTreeStatus>,
+    pub operation: Option<GitOperation>,
+    /// Submodules not at the commit the superproject records
+    pub stale_submodules: Vec<String>,
+    /// Ahead/behind against the remote's default branch, when that differs
+    /// from the upstream
+    pub vs_default: Option<RefComparison>,
+    /// Ahead/behind against `@{push}`, when that differs from the upstream
+    pub vs_push: Option<RefComparison>,
+    pub pull_result: Option<PullResult>,
+    /// Where `git push` would push the branch
+    pub push: Option<PushTarget>,
+    pub push_result: Option<PushResult>,
+    /// Post-pull hooks run after this worktree was pulled
+    pub hooks: Vec<HookOutcome>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    /// Short ref name, e.g. `origin/feature-x`
+    pub name: String,
+    /// Configured, but the remote branch no longer exists (pruned)
+    pub gone: bool,
+    /// Commit the upstream pointed at when status was gathered
+    pub oid: Option<String>,
+}
+
+impl WorktreeInfo {
+    pub fn upstream_gone(&self) -> bool {
+        self.upstream.as_ref().is_some_and(|u| u.gone)
+    }
+
+    /// Upstream is gone and HEAD is already contained in the default branch
+    pub fn merged_and_gone(&self) -> bool {
+        self.upstream_gone() && matches!(&self.vs_default, Some(cmp) if cmp.ahead_behind.0 == 0)
+    }
+}
+
+/// Ahead/behind of HEAD relative to some other ref
+#[derive(Debug, Clone)]
+pub struct RefComparison {
+    /// Short ref name, e.g. `origin/main`
+    pub name: String,
+    pub ahead_behind: (usize, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalBranch {
+    pub name: String,
+    /// Commit the branch pointed at when it was listed
+    pub oid: String,
+    /// Full upstream ref, e.g. `refs/remotes/origin/main`
+    pub upstream: Option<String>,
+    /// Commit the upstream pointed at when the branch was listed
+    pub upstream_oid: Option<String>,
+    /// Remote the upstream belongs to
+    pub remote: Option<String>,
+    /// Upstream is configured but its remote-tracking ref no longer exists
+    pub upstream_gone: bool,
+    /// Ahead/behind of the upstream; `None` without a (live) upstream
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Worktree the branch is checked out in, if any
+    pub worktree: Option<PathBuf>,
+    /// Remote `git push` would push the branch to
+    pub push_remote: Option<String>,
+    /// Ref on the push remote, e.g. `refs/heads/feature-x`
+    pub push_ref: Option<String>,
+    /// Local tracking ref for the push destination (`@{push}`)
+    pub push_tracking: Option<String>,
+    /// Set when the branch was fast-forwarded without a checkout
     pub pull_result: Option<PullResult>,
 }
 
+/// A multi-step git operation left in progress in a worktree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitOperation {
+    Rebase,
+    /// `git am`, which shares the `rebase-apply` directory with rebase
+    Am,
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+impl GitOperation {
+    pub fn label(self) -> &'static str {
+        match self {
+            GitOperation::Rebase => "rebase",
+            GitOperation::Am => "am",
+            GitOperation::Merge => "merge",
+            GitOperation::CherryPick => "cherry-pick",
+            GitOperation::Revert => "revert",
+            GitOperation::Bisect => "bisect",
+        }
+    }
+}
+
+/// Uncommitted changes, counted from `git status --porcelain=v2`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkingTreeStatus {
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    /// Unstaged changes that are only a submodule checked out at another
+    /// commit than recorded (also counted in `unstaged`)
+    pub submodule_pointers: usize,
+}
+
+impl WorkingTreeStatus {
+    pub fn is_dirty(&self) -> bool {
+        self.staged + self.unstaged + self.untracked + self.conflicted > 0
+    }
+
+    /// Dirty apart from submodules that are merely at another commit, which
+    /// a submodule update would fix
+    pub fn is_dirty_besides_submodule_pointers(&self) -> bool {
+        self.staged + (self.unstaged - self.submodule_pointers) + self.untracked + self.conflicted
+            > 0
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum PullResult {
     Pulled,
+    /// Local commits were rebased onto the upstream
+    Rebased,
+    /// The rebase conflicted and was aborted; nothing changed
+    RebaseAborted,
+    /// Eligible, but excluded by the auto-pull allow/deny rules
+    NotAllowed,
+    /// Not attempted, e.g. because the worktree is dirty
+    Skipped(String),
     Failed(String),
 }
 
+#[derive(Debug, Clone)]
+pub struct PushTarget {
+    pub remote: String,
+    /// Ref on the remote, e.g. `refs/heads/feature-x`
+    pub remote_ref: String,
+    /// Local tracking ref of the destination (`@{push}`), e.g.
+    /// `refs/remotes/fork/feature-x`; it doesn't exist until first pushed
+    pub tracking: String,
+    /// The destination is the upstream, so `ahead_behind` already
+    /// compares against it
+    pub is_upstream: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PushResult {
+    Pushed,
+    /// Dry run: git accepted the push but nothing was sent
+    WouldPush,
+    PushFailed(String),
+}
+
+/// Result of one post-pull hook command
+#[derive(Debug, Clone)]
+pub struct HookOutcome {
+    pub command: String,
+    pub success: bool,
+    /// `None` if the command couldn't be started or was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Captured stdout followed by stderr
+    pub output: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StashEntry {
+    /// Position in the stash list (`stash@{index}`)
+    pub index: usize,
+    /// Branch the stash was made on; `None` if HEAD was detached
+    pub branch: Option<String>,
+    pub message: String,
+    pub created: SystemTime,
+}
+
 #[derive(Debug, Clone)]
 pub struct RepoStatus {
     pub repo: DiscoveredRepo,
     pub fetch_outcome: FetchOutcome,
     pub worktrees: Vec<WorktreeInfo>,
+    pub stashes: Vec<StashEntry>,
+    /// Every local branch; only collected with `all_branches` or `ff_branches`
+    pub branches: Vec<LocalBranch>,
+}
+
+impl RepoStatus {
+    /// Copy each worktree's ahead/behind to its branch in `branches`, which
+    /// was gathered before auto-pull or auto-push moved anything
+    pub fn sync_branches(&mut self) {
+        for wt in &self.worktrees {
+            let Some(name) = &wt.branch else {
+                continue;
+            };
+            if let Some(branch) = self.branches.iter_mut().find(|b| &b.name == name) {
+                branch.ahead_behind = wt.ahead_behind;
+            }
+        }
+    }
+}
+
+/// Per-remote-host fetch limits, applied on top of the global concurrency cap.
+#[derive(Debug, Clone, Default)]
+pub struct HostLimits {
+    /// Limit for hosts without an explicit entry; `None` means only the global cap applies
+    pub default: Option<usize>,
+    /// Limits keyed by lowercase host name
+    pub hosts: HashMap<String, usize>,
+}
+
+impl HostLimits {
+    pub fn limit_for(&self, host: &str) -> Option<usize> {
+        self.hosts.get(host).copied().or(self.default)
+    }
+}
+
+/// What to do with in-flight fetches on Ctrl-C
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptPolicy {
+    /// Stop running git processes (SIGTERM, killed if they linger) and mark
+    /// their repos cancelled
+    #[default]
+    Terminate,
+    /// Let running fetches complete, but start no new ones
+    Wait,
+}
+
+/// What auto-pull does with worktrees that have uncommitted changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyPolicy {
+    /// Leave them alone and report them as skipped
+    #[default]
+    Skip,
+    /// Stash changes around the fast-forward (`git merge --autostash`)
+    Autostash,
+    /// Fast-forward anyway; git refuses if changes would be overwritten
+    Attempt,
+}
+
+/// How auto-pull updates worktrees that are behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PullStrategy {
+    /// Only fast-forward; worktrees with local commits are left alone
+    #[default]
+    FfOnly,
+    /// Also rebase clean worktrees that have diverged
+    Rebase,
+}
+
+/// Which repos and branches auto-pull may touch; deny wins over allow
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PullPolicy {
+    pub allow: PolicyRule,
+    pub deny: PolicyRule,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PolicyRule {
+    /// Repo path globs
+    pub repos: Vec<String>,
+    /// Branch name patterns, e.g. `release/*`
+    pub branches: Vec<String>,
+    /// Names of repo groups from the `groups` config
+    pub groups: Vec<String>,
+}
+
+/// Shell command run in a worktree after auto-pull moved it
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostPullHook {
+    /// Repo path globs; empty means every repo
+    #[serde(default)]
+    pub repos: Vec<String>,
+    pub run: String,
+}
+
+/// How results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// See `report` for the schema
+    Json,
+    /// One `report::Event` per line, as they happen
+    Ndjson,
+}
+
+/// Restricts output to worktrees in a given state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorktreeFilter {
+    Dirty,
+    Staged,
+    Unstaged,
+    Untracked,
+    Conflicted,
+    InProgress,
+    Gone,
 }
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub directories: Vec<PathBuf>,
     pub concurrency: usize,
+    pub host_limits: HostLimits,
+    pub status_concurrency: usize,
     pub fetch: bool,
+    pub min_fetch_interval: Option<Duration>,
+    pub on_interrupt: InterruptPolicy,
     pub emoji: bool,
     pub auto_pull: bool,
+    pub auto_pull_dirty: DirtyPolicy,
+    pub auto_pull_strategy: PullStrategy,
+    /// Sync and update submodules after a successful pull
+    pub auto_pull_submodules: bool,
+    pub auto_pull_policy: PullPolicy,
+    /// Named sets of repo path globs
+    pub groups: HashMap<String, Vec<String>>,
+    pub post_pull: Vec<PostPullHook>,
+    pub auto_push: bool,
+    /// Branch name patterns auto-push may push; empty means any
+    pub auto_push_branches: Vec<String>,
+    pub push_dry_run: bool,
+    pub hook_concurrency: usize,
+    /// Fast-forward branches that are not checked out anywhere
+    pub ff_branches: bool,
     pub max_depth: usize,
     pub show_all: bool,
+    /// Report every local branch, not just the checked-out ones
+    pub all_branches: bool,
+    pub filters: Vec<WorktreeFilter>,
+    /// Only show repos with a stash older than this
+    pub stashes_older_than: Option<Duration>,
+    pub format: OutputFormat,
+    pub quiet: bool,
 }
 
 impl Default for AppConfig {
@@ -65,11 +407,33 @@ impl Default for AppConfig {
         Self {
             directories: Vec::new(),
             concurrency: 5,
+            host_limits: HostLimits::default(),
+            status_concurrency: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
             fetch: true,
+            min_fetch_interval: None,
+            on_interrupt: InterruptPolicy::default(),
             emoji: true,
             auto_pull: false,
+            auto_pull_dirty: DirtyPolicy::default(),
+            auto_pull_strategy: PullStrategy::default(),
+            auto_pull_submodules: false,
+            auto_pull_policy: PullPolicy::default(),
+            groups: HashMap::new(),
+            post_pull: Vec::new(),
+            auto_push: false,
+            auto_push_branches: Vec::new(),
+            push_dry_run: false,
+            hook_concurrency: 2,
+            ff_branches: false,
             max_depth: 3,
             show_all: false,
+            all_branches: false,
+            filters: Vec::new(),
+            stashes_older_than: None,
+            format: OutputFormat::default(),
+            quiet: false,
         }
     }
 }
@@ -0,0 +1,210 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+use owo_colors::Stream::Stdout;
+
+use crate::git;
+use crate::journal::{self, Journal, RefChange};
+use crate::output::format_age;
+
+/// Restore every ref moved by the most recent journaled run.
+///
+/// A ref is only restored if it still points where that run left it. The
+/// restores are journaled as a run of their own, so undoing twice puts the
+/// changes back.
+pub async fn undo_last_run() -> Result<()> {
+    let path = journal::journal_path().context("no state directory to read the journal from")?;
+    let entries = journal::read(&path)?;
+
+    let Some(last) = entries.iter().map(|e| e.run).max() else {
+        println!("Nothing to undo.");
+        return Ok(());
+    };
+    let run: Vec<_> = entries.iter().filter(|e| e.run == last).collect();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let age = Duration::from_secs(now.saturating_sub(run[0].timestamp));
+    println!(
+        "Undoing {} change(s) from {} ago",
+        run.len().if_supports_color(Stdout, |t| t.bold()),
+        format_age(age)
+    );
+
+    let journal = Journal::open();
+    let mut refused = 0;
+
+    // Newest first, in case a ref moved more than once
+    for entry in run.iter().rev() {
+        let change = &entry.change;
+        let repo_name = change
+            .repo
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| change.repo.display().to_string());
+        let branch = change
+            .refname
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&change.refname);
+
+        match restore(change).await {
+            Ok(()) => {
+                println!(
+                    "  {} {}: restored {} (was {})",
+                    repo_name.if_supports_color(Stdout, |t| t.bold()),
+                    branch.if_supports_color(Stdout, |t| t.cyan()),
+                    short(&change.old),
+                    short(&change.new)
+                );
+                journal.record(RefChange {
+                    old: change.new.clone(),
+                    new: change.old.clone(),
+                    ..change.clone()
+                });
+            }
+            Err(e) => {
+                refused += 1;
+                println!(
+                    "  {} {}: {}",
+                    repo_name.if_supports_color(Stdout, |t| t.bold()),
+                    branch.if_supports_color(Stdout, |t| t.cyan()),
+                    format!("not undone: {}", e).if_supports_color(Stdout, |t| t.red())
+                );
+            }
+        }
+    }
+
+    if refused > 0 {
+        anyhow::bail!("{} change(s) could not be undone", refused);
+    }
+    Ok(())
+}
+
+/// Move a ref back from `change.new` to `change.old`, refusing if it has
+/// moved since
+async fn restore(change: &RefChange) -> Result<()> {
+    let current = git::rev_parse(&change.repo, change.kind, &change.refname).await?;
+    if current != change.new {
+        anyhow::bail!("moved since (now at {})", short(&current));
+    }
+
+    match &change.worktree {
+        // Checked out: move the branch and the working tree together
+        Some(worktree) => {
+            let head = git::symbolic_head(worktree).await.unwrap_or_default();
+            if head != change.refname {
+                anyhow::bail!("no longer checked out in {}", worktree.display());
+            }
+            git::reset_keep(worktree, &change.old).await
+        }
+        None => {
+            git::update_ref(
+                &change.repo,
+                change.kind,
+                &change.refname,
+                &change.old,
+                &change.new,
+                "undo",
+            )
+            .await
+        }
+    }
+}
+
+fn short(oid: &str) -> &str {
+    &oid[..7.min(oid.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use super::*;
+    use crate::types::RepoKind;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}: {:?}", args, output);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// A repo on `main` with one commit per name, returning their ids
+    fn repo_with_commits(names: &[&str]) -> (tempfile::TempDir, PathBuf, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        git(dir.path(), &["init", "-q", "-b", "main", "work"]);
+        let commits = names
+            .iter()
+            .map(|name| {
+                std::fs::write(work.join(name), name).unwrap();
+                git(&work, &["add", name]);
+                git(&work, &["commit", "-q", "-m", name]);
+                git(&work, &["rev-parse", "HEAD"])
+            })
+            .collect();
+        (dir, work, commits)
+    }
+
+    fn change(work: &Path, worktree: Option<&Path>, old: &str, new: &str) -> RefChange {
+        RefChange {
+            repo: work.to_path_buf(),
+            kind: RepoKind::NonBare,
+            worktree: worktree.map(Path::to_path_buf),
+            refname: "refs/heads/main".to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_a_ref_that_moved_since() {
+        let (_dir, work, commits) = repo_with_commits(&["a", "b", "c"]);
+        git(&work, &["branch", "other", &commits[1]]);
+        let mut moved = change(&work, None, &commits[0], &commits[1]);
+        moved.refname = "refs/heads/other".to_string();
+        git(&work, &["branch", "-f", "other", &commits[2]]);
+
+        let err = restore(&moved).await.unwrap_err();
+        assert!(err.to_string().contains("moved since"), "{}", err);
+        assert_eq!(git(&work, &["rev-parse", "other"]), commits[2]);
+    }
+
+    #[tokio::test]
+    async fn moves_a_checked_out_branch_back_with_its_working_tree() {
+        let (_dir, work, commits) = repo_with_commits(&["a", "b"]);
+        std::fs::write(work.join("notes"), "untracked").unwrap();
+
+        restore(&change(&work, Some(&work), &commits[0], &commits[1]))
+            .await
+            .unwrap();
+
+        assert_eq!(git(&work, &["rev-parse", "HEAD"]), commits[0]);
+        assert_eq!(git(&work, &["symbolic-ref", "HEAD"]), "refs/heads/main");
+        // reset --keep updated the files and kept local ones
+        assert!(!work.join("b").exists());
+        assert!(work.join("notes").exists());
+    }
+
+    #[tokio::test]
+    async fn moves_a_branch_that_is_not_checked_out_back() {
+        let (_dir, work, commits) = repo_with_commits(&["a", "b"]);
+        git(&work, &["checkout", "-q", "-b", "side"]);
+
+        restore(&change(&work, None, &commits[0], &commits[1]))
+            .await
+            .unwrap();
+
+        assert_eq!(git(&work, &["rev-parse", "main"]), commits[0]);
+        assert!(work.join("b").exists());
+    }
+}